@@ -1,186 +1,4273 @@
 slint::include_modules!();
-use slint::{Timer, TimerMode, Color};
+use slint::{CloseRequestResponse, Timer, TimerMode, Color, Model, ModelRc, SharedString, VecModel};
+use slint::winit_030::WinitWindowAccessor;
 use std::rc::Rc;
-use std::cell::RefCell;
+use std::cell::{OnceCell, RefCell};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::thread;
-use std::io::BufReader;
+use std::borrow::Cow;
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::{Duration, Instant};
 use serde::{Serialize, Deserialize};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime};
+use directories::ProjectDirs;
 use notify_rust::Notification;
-use rodio::{Decoder, OutputStream, Sink};
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
+use rodio::source::Buffered;
+use tray_icon::{Icon, TrayIcon, TrayIconBuilder};
+use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+use global_hotkey::hotkey::HotKey;
+use tray_icon::menu::{Menu, MenuEvent, MenuItem};
 
 #[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
 struct AppConfig {
     work_m: i32,
     short_m: i32,
     long_m: i32,
+    /// Exact lengths for sub-minute or odd phases; when set they replace the minutes.
+    work_s: Option<i32>,
+    short_s: Option<i32>,
+    long_s: Option<i32>,
     alarm_path: String,
+    /// Per-phase overrides; empty means "use `alarm_path`", so configs from before
+    /// these existed keep their single alarm for both phases.
+    work_alarm_path: String,
+    break_alarm_path: String,
+    /// Plays instead of the focus alarm when a work phase leads into the long break.
+    cycle_complete_alarm_path: String,
+    /// Short, quieter heads-up this many seconds before a phase ends; 0 turns it off.
+    warn_before_seconds: i32,
+    /// Sound for the heads-up; empty uses `alarm_path`.
+    warn_alarm_path: String,
+    auto_start_breaks: bool,
+    auto_start_work: bool,
+    /// Skipped focus phases still count as pomodoros (sessions, stats, goal, long-break cycle).
+    count_skipped_work: bool,
+    /// Off: every focus phase is followed by a short break; sessions still count as usual.
+    long_break_enabled: bool,
+    sessions_per_long_break: i32,
+    alarm_volume: f32,
+    alarm_loop: bool,
+    alarm_fade: bool,
+    /// Longest one play-through of the alarm may run; 0 plays the whole file.
+    max_alarm_seconds: i32,
+    count_up: bool,
+    timer_kind: TimerKind,
+    notifications_enabled: bool,
+    /// Summary line of every notification; empty falls back to `DEFAULT_APP_TITLE`.
+    app_title: String,
+    work_done_message: String,
+    break_done_message: String,
+    hotkey_toggle: String,
+    hotkey_reset: String,
+    always_on_top: bool,
+    theme: String,
+    /// Code from `LANGUAGES` for the phase labels and messages; unknown codes show English.
+    language: String,
+    manage_dnd: bool,
+    strict_mode: bool,
+    invert_progress: bool,
+    /// Shade the progress bar from the phase color toward red as the phase runs out.
+    urgency_gradient: bool,
+    schedule_mode: ScheduleMode,
+    long_break_after_m: i32,
+    webhook_url: String,
+    event_log: bool,
+    visual_alarm: bool,
+    /// Last window size in logical pixels; `None` until the user first resizes.
+    window_width: Option<u32>,
+    window_height: Option<u32>,
+    /// Physical top-left corner, only restored while it's still on a connected monitor.
+    window_x: Option<i32>,
+    window_y: Option<i32>,
+    /// Borderless countdown-only view; the saved size above is the full view's.
+    mini_mode: bool,
+    snooze_m: i32,
+    /// Snoozes allowed in a row for one phase end; 0 means no limit.
+    max_snoozes: i32,
+    auto_pause_on_idle: bool,
+    idle_pause_m: i32,
+    /// Pause focus while the screen is locked and resume on unlock.
+    pause_on_lock: bool,
+    /// `#rrggbb` phase colors; anything unparsable shows the built-in color.
+    work_color: String,
+    short_break_color: String,
+    long_break_color: String,
+    /// Ask before quitting while a focus phase is running.
+    confirm_quit: bool,
+    /// Begin a focus phase as soon as the app opens.
+    autostart_on_launch: bool,
+    /// Serve the timer state as JSON on 127.0.0.1:`status_port`.
+    status_server: bool,
+    status_port: u16,
+    /// Accept `pause`/`resume`/`toggle`/`skip`/`reset` on a Unix socket (named pipe on Windows).
+    command_socket: bool,
+    /// Socket path or pipe name; empty uses `command.sock` in the data dir, or `\\.\pipe\pomodoro_timer`.
+    command_socket_path: String,
+    /// Soft once-a-second tick while a focus phase runs.
+    focus_tick: bool,
+    focus_tick_volume: f32,
+    /// Audio file looped while a focus phase runs (rain, café, ...); empty for none.
+    ambient_path: String,
+    ambient_volume: f32,
+    /// Pomodoros to aim for each day; 0 turns the goal off.
+    daily_goal: i32,
+    /// Summarise the day in a notification at `day_summary_time` (`HH:MM`); left empty,
+    /// the summary comes when the app quits instead.
+    day_summary: bool,
+    day_summary_time: String,
+    /// Nudge toward a break once this many focus minutes have run without one; 0 turns it off.
+    break_reminder_m: i32,
 }
 
 impl Default for AppConfig {
     fn default() -> Self {
-        Self { work_m: 25, short_m: 5, long_m: 15, alarm_path: "alarm.mp3".to_string() }
+        Self {
+            work_m: 25,
+            short_m: 5,
+            long_m: 15,
+            work_s: None,
+            short_s: None,
+            long_s: None,
+            alarm_path: String::new(),
+            work_alarm_path: String::new(),
+            break_alarm_path: String::new(),
+            cycle_complete_alarm_path: String::new(),
+            warn_before_seconds: 0,
+            warn_alarm_path: String::new(),
+            auto_start_breaks: true,
+            auto_start_work: false,
+            count_skipped_work: false,
+            long_break_enabled: true,
+            sessions_per_long_break: 4,
+            alarm_volume: 0.8,
+            alarm_loop: false,
+            alarm_fade: false,
+            max_alarm_seconds: 15,
+            count_up: false,
+            timer_kind: TimerKind::Pomodoro,
+            notifications_enabled: true,
+            app_title: DEFAULT_APP_TITLE.to_string(),
+            work_done_message: String::new(),
+            break_done_message: String::new(),
+            hotkey_toggle: "Ctrl+Alt+P".to_string(),
+            hotkey_reset: "Ctrl+Alt+R".to_string(),
+            always_on_top: false,
+            theme: "dark".to_string(),
+            language: "en".to_string(),
+            manage_dnd: false,
+            strict_mode: false,
+            invert_progress: false,
+            urgency_gradient: false,
+            schedule_mode: ScheduleMode::SessionCount,
+            long_break_after_m: 100,
+            webhook_url: String::new(),
+            event_log: false,
+            visual_alarm: false,
+            window_width: None,
+            window_height: None,
+            window_x: None,
+            window_y: None,
+            mini_mode: false,
+            snooze_m: 2,
+            max_snoozes: 3,
+            auto_pause_on_idle: false,
+            idle_pause_m: 5,
+            pause_on_lock: false,
+            work_color: DEFAULT_COLORS[0].to_string(),
+            short_break_color: DEFAULT_COLORS[1].to_string(),
+            long_break_color: DEFAULT_COLORS[2].to_string(),
+            confirm_quit: true,
+            autostart_on_launch: false,
+            status_server: false,
+            status_port: 7425,
+            command_socket: false,
+            command_socket_path: String::new(),
+            focus_tick: false,
+            focus_tick_volume: 0.2,
+            ambient_path: String::new(),
+            ambient_volume: 0.3,
+            daily_goal: 8,
+            break_reminder_m: 90,
+            day_summary: true,
+            day_summary_time: "18:00".to_string(),
+        }
+    }
+}
+
+/// The configured phase lengths, kept aside while CLI overrides are in effect.
+#[derive(Clone, Copy, PartialEq)]
+struct Durations {
+    minutes: [i32; 3],
+    seconds: [Option<i32>; 3],
+}
+
+impl AppConfig {
+    fn phase_seconds(&self, mode: Mode) -> i32 {
+        match mode {
+            Mode::Work => self.work_s.unwrap_or(self.work_m * 60),
+            Mode::ShortBreak => self.short_s.unwrap_or(self.short_m * 60),
+            Mode::LongBreak => self.long_s.unwrap_or(self.long_m * 60),
+        }
+    }
+
+    fn durations(&self) -> Durations {
+        Durations { minutes: [self.work_m, self.short_m, self.long_m], seconds: [self.work_s, self.short_s, self.long_s] }
+    }
+
+    fn set_durations(&mut self, d: Durations) {
+        [self.work_m, self.short_m, self.long_m] = d.minutes;
+        [self.work_s, self.short_s, self.long_s] = d.seconds;
+    }
+
+    fn long_break_interval(&self) -> i32 {
+        if self.sessions_per_long_break > 0 { self.sessions_per_long_break } else { 4 }
+    }
+
+    fn long_break_after_seconds(&self) -> i32 {
+        if self.long_break_after_m > 0 { self.long_break_after_m * 60 } else { 100 * 60 }
     }
+
+    fn alarm_path(&self, target: AlarmTarget) -> &str {
+        match target {
+            AlarmTarget::Shared => &self.alarm_path,
+            AlarmTarget::Work => &self.work_alarm_path,
+            AlarmTarget::Break => &self.break_alarm_path,
+            AlarmTarget::Cycle => &self.cycle_complete_alarm_path,
+            AlarmTarget::Warning => &self.warn_alarm_path,
+        }
+    }
+
+    fn alarm_path_mut(&mut self, target: AlarmTarget) -> &mut String {
+        match target {
+            AlarmTarget::Shared => &mut self.alarm_path,
+            AlarmTarget::Work => &mut self.work_alarm_path,
+            AlarmTarget::Break => &mut self.break_alarm_path,
+            AlarmTarget::Cycle => &mut self.cycle_complete_alarm_path,
+            AlarmTarget::Warning => &mut self.warn_alarm_path,
+        }
+    }
+
+    /// The file that actually plays for `target`, after falling back to the shared
+    /// alarm (the cycle alarm falls back to the focus one first).
+    fn effective_alarm(&self, target: AlarmTarget) -> &str {
+        let path = self.alarm_path(target);
+        match target {
+            _ if !path.is_empty() => path,
+            AlarmTarget::Cycle => self.effective_alarm(AlarmTarget::Work),
+            _ => &self.alarm_path,
+        }
+    }
+
+    fn alarm_style(&self) -> AlarmStyle {
+        AlarmStyle {
+            volume: self.alarm_volume.clamp(0.0, 1.0),
+            looped: self.alarm_loop,
+            fade: self.alarm_fade,
+            max_duration: (self.max_alarm_seconds > 0).then(|| Duration::from_secs(self.max_alarm_seconds as u64)),
+        }
+    }
+
+    /// How much of an alarm file is worth decoding: no play-through gets past the cap,
+    /// though the heads-up chime can play up to `WARNING_LENGTH` of the same file.
+    fn alarm_buffer_limit(&self) -> Option<Duration> {
+        self.alarm_style().max_duration.map(|max| max.max(WARNING_LENGTH))
+    }
+}
+
+/// What earns a long break: every Nth completed session, or enough focus time.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+enum ScheduleMode {
+    #[default]
+    SessionCount,
+    FocusMinutes,
 }
 
+/// Which alarm setting the UI is editing: the shared one, the one ending a focus
+/// phase or a break, or the heads-up. Matches the index of the alarm ComboBox.
 #[derive(Clone, Copy, PartialEq)]
+enum AlarmTarget { Shared, Work, Break, Cycle, Warning }
+
+impl AlarmTarget {
+    fn from_index(index: i32) -> Self {
+        match index {
+            1 => AlarmTarget::Work,
+            2 => AlarmTarget::Break,
+            3 => AlarmTarget::Cycle,
+            4 => AlarmTarget::Warning,
+            _ => AlarmTarget::Shared,
+        }
+    }
+
+    /// The alarm that sounds when `ended` runs out and `next` begins.
+    fn ending(ended: Mode, next: Mode) -> Self {
+        match (ended, next) {
+            (Mode::Work, Mode::LongBreak) => AlarmTarget::Cycle,
+            (Mode::Work, _) => AlarmTarget::Work,
+            _ => AlarmTarget::Break,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct AlarmStyle {
+    volume: f32,
+    looped: bool,
+    fade: bool,
+    max_duration: Option<Duration>,
+}
+
+const DEFAULT_PROFILE: &str = "Default";
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Profile {
+    name: String,
+    #[serde(flatten)]
+    config: AppConfig,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct ConfigFile {
+    active_profile: String,
+    profiles: Vec<Profile>,
+}
+
+impl Default for ConfigFile {
+    fn default() -> Self {
+        Self::single(AppConfig::default())
+    }
+}
+
+impl ConfigFile {
+    fn single(config: AppConfig) -> Self {
+        Self { active_profile: DEFAULT_PROFILE.to_string(), profiles: vec![Profile { name: DEFAULT_PROFILE.to_string(), config }] }
+    }
+
+    fn parse(data: &str) -> serde_json::Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(data)?;
+        // Files from before profiles existed hold a single bare AppConfig.
+        let mut file = if value.get("profiles").is_some() {
+            serde_json::from_value::<ConfigFile>(value)?
+        } else {
+            ConfigFile::single(serde_json::from_value(value)?)
+        };
+        if file.profiles.is_empty() {
+            file.profiles.push(Profile { name: DEFAULT_PROFILE.to_string(), config: AppConfig::default() });
+        }
+        if !file.profiles.iter().any(|p| p.name == file.active_profile) {
+            eprintln!("warning: active profile \"{}\" doesn't exist; using \"{}\"", file.active_profile, file.profiles[0].name);
+            file.active_profile = file.profiles[0].name.clone();
+        }
+        Ok(file)
+    }
+
+    /// Reads a settings file picked for import. Unlike `load_config` nothing falls back to
+    /// defaults here: any problem rejects the whole file so nothing half-applies.
+    fn import(path: &Path) -> Result<Self, String> {
+        let data = fs::read_to_string(path).map_err(|e| format!("Can't read the file ({e})"))?;
+        let value: serde_json::Value = serde_json::from_str(&data).map_err(|e| format!("Not valid JSON ({e})"))?;
+        if !value.is_object() { return Err("Not a settings file".to_string()); }
+        let file = ConfigFile::parse(&data).map_err(|e| format!("Not a settings file ({e})"))?;
+        for profile in &file.profiles {
+            if profile.name.trim().is_empty() { return Err("A profile has no name".to_string()); }
+            if file.profiles.iter().filter(|p| p.name == profile.name).count() > 1 {
+                return Err(format!("Profile \"{}\" appears twice", profile.name));
+            }
+            let c = &profile.config;
+            if [c.work_m, c.short_m, c.long_m].iter().any(|&m| !(MIN_MINUTES..=MAX_MINUTES).contains(&m)) {
+                return Err(format!("Profile \"{}\" has a duration outside {MIN_MINUTES}-{MAX_MINUTES} minutes", profile.name));
+            }
+            if [c.work_s, c.short_s, c.long_s].iter().flatten().any(|s| !(MIN_SECONDS..=MAX_MINUTES * 60).contains(s)) {
+                return Err(format!("Profile \"{}\" has a duration outside {MIN_SECONDS}-{} seconds", profile.name, MAX_MINUTES * 60));
+            }
+        }
+        Ok(file)
+    }
+
+    fn active(&self) -> &AppConfig {
+        &self.profiles.iter().find(|p| p.name == self.active_profile).unwrap_or(&self.profiles[0]).config
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+struct Stats {
+    total_sessions: i32,
+    total_focus_seconds: i64,
+    last_active_date: Option<NaiveDate>,
+    today_sessions: i32,
+    today_focus_seconds: i32,
+    /// Completed cycles, i.e. work phases that led into a long break.
+    total_cycles: i32,
+    today_cycles: i32,
+    total_break_seconds: i64,
+    today_break_seconds: i32,
+    today_date: Option<NaiveDate>,
+    /// Consecutive local days, ending on `last_active_date`, with a completed phase.
+    streak_days: i32,
+    /// Today's goal has been celebrated; cleared at midnight or when the goal moves past today's count.
+    goal_celebrated: bool,
+    /// Entries of `MILESTONES` already celebrated, so none fires twice.
+    milestones_celebrated: Vec<i32>,
+    /// Today's summary notification went out; cleared at midnight.
+    day_summary_shown: bool,
+}
+
+/// Lifetime pomodoro counts that get their own notification.
+const MILESTONES: &[i32] = &[50, 100, 250, 500, 1000];
+
+impl Stats {
+    /// Resets the per-day counters when `today` differs from the stored day.
+    fn roll_day(&mut self, today: NaiveDate) -> bool {
+        if self.today_date == Some(today) { return false; }
+        self.today_date = Some(today);
+        self.today_sessions = 0;
+        self.today_focus_seconds = 0;
+        self.today_cycles = 0;
+        self.today_break_seconds = 0;
+        self.goal_celebrated = false;
+        self.day_summary_shown = false;
+        true
+    }
+
+    /// Marks `today` as used, extending the streak if the last active day was yesterday.
+    fn record_active(&mut self, today: NaiveDate) {
+        // `max(1)` covers stats files written before the streak was tracked.
+        self.streak_days = match self.last_active_date {
+            Some(day) if day == today => self.streak_days.max(1),
+            Some(day) if day.succ_opt() == Some(today) => self.streak_days.max(1) + 1,
+            _ => 1,
+        };
+        self.last_active_date = Some(today);
+    }
+
+    /// The streak as of `today`: still alive if it was extended today or yesterday.
+    fn current_streak(&self, today: NaiveDate) -> i32 {
+        match self.last_active_date {
+            Some(day) if day == today || day.succ_opt() == Some(today) => self.streak_days,
+            _ => 0,
+        }
+    }
+
+    /// Share of today's completed phase time spent focusing, as a whole percent.
+    /// `None` until anything has been completed today.
+    fn today_focus_ratio(&self) -> Option<i32> {
+        let total = self.today_focus_seconds as i64 + self.today_break_seconds as i64;
+        (total > 0).then(|| (self.today_focus_seconds as i64 * 100 / total) as i32)
+    }
+}
+
+/// Outcome of a phase change. The notification is handed back rather than shown
+/// inline so callers can display it after releasing the `AppState` borrow.
+struct Transition {
+    auto_start: bool,
+    notice: Option<String>,
+    /// The session just written to the log, for the in-window history list.
+    logged: Option<HistoryEntry>,
+}
+
+type AlarmSlot = Arc<Mutex<Option<Arc<Sink>>>>;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 enum Mode { Work, ShortBreak, LongBreak }
 
-#[derive(Clone)]
+/// Fixed-length phases with breaks in between, or a focus stopwatch that counts up
+/// until it's stopped and logged by hand. The stopwatch stays in `Mode::Work`.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+enum TimerKind {
+    #[default]
+    Pomodoro,
+    Stopwatch,
+}
+
+/// Timer position written to `session_state.json` so a pomodoro survives a restart.
+/// A running phase is stored by its wall-clock end rather than the seconds left.
+#[derive(Serialize, Deserialize)]
+struct SessionState {
+    mode: Mode,
+    sessions_completed: i32,
+    #[serde(default)]
+    focus_since_long_break: i32,
+    seconds_left: i32,
+    ends_at: Option<DateTime<Local>>,
+    /// Stopwatch time so far; a running stopwatch carries on from here, not from when it closed.
+    #[serde(default)]
+    stopwatch_seconds: Option<i32>,
+    /// Profile the phase ran under; missing in files from before it was stored.
+    #[serde(default)]
+    profile: Option<String>,
+}
+
+impl SessionState {
+    /// Seconds still left now; zero or less means the phase ran out while closed.
+    fn remaining(&self) -> i64 {
+        match self.ends_at {
+            Some(end) => (end - Local::now()).num_seconds(),
+            None => self.seconds_left as i64,
+        }
+    }
+
+    /// A pomodoro phase that ended while the app was closed. A stopwatch never runs out.
+    fn ran_out(&self) -> bool {
+        self.stopwatch_seconds.is_none() && self.remaining() <= 0
+    }
+}
+
 struct AppState {
     seconds_left: i32,
     mode: Mode,
     sessions_completed: i32,
+    /// Focus seconds completed since the last long break, for `ScheduleMode::FocusMinutes`.
+    /// Tracked in both modes so switching schedules mid-cycle keeps a sensible count.
+    focus_since_long_break: i32,
     config: AppConfig,
+    stats: Stats,
+    deadline: Instant,
+    paused_at: Option<Instant>,
+    accumulated_pause: Duration,
+    alarm: AlarmSlot,
+    saved_durations: Option<Durations>,
+    profiles: Vec<Profile>,
+    active_profile: String,
+    alarm_cache: Vec<CachedAlarm>,
+    output: Option<(OutputStream, OutputStreamHandle)>,
+    dnd_active: bool,
+    /// Set when the idle or screen-lock check paused focus, so coming back resumes it
+    /// but a manual pause sticks.
+    auto_paused: bool,
+    /// Last values the tick wrote to the window, so it only re-renders text on change.
+    rendered_second: Option<i32>,
+    rendered_pause: Option<u64>,
+    /// `--log` turns the event log on for this run regardless of `config.event_log`.
+    log_forced: bool,
+    /// Minutes added to the current phase with `extend`, in seconds (negative when an
+    /// ended phase was reopened for less than its normal length).
+    extra_seconds: i32,
+    /// The phase that just ran out, while it can still be reopened with `extend`.
+    just_ended: Option<Mode>,
+    /// Set while a reopened phase runs: the phase to continue with afterwards.
+    resume_to: Option<Mode>,
+    /// Snoozes taken since the last phase that ended normally.
+    snoozes: i32,
+    /// Focus seconds since the last break that ran out, across extends and skipped breaks.
+    focus_without_break: i32,
+    /// Break reminders sent during the current `focus_without_break` stretch.
+    break_reminders: i32,
+    status_server: Option<StatusServer>,
+    command_socket: Option<CommandSocket>,
+    /// When start/pause last went through, to drop the repeats of a double click or held key.
+    last_toggle: Option<Instant>,
+    /// Sink playing the focus tick; separate from the alarm's so each can stop alone.
+    tick: Option<Sink>,
+    /// Sink looping the ambient sound, likewise on its own, with the file it plays. No
+    /// sink means the file didn't open, so it isn't retried on every tick.
+    ambient: Option<(String, Option<Sink>)>,
+    /// What the last reset threw away, while its Undo is still offered.
+    undo_reset: Option<ResetUndo>,
+    /// The heads-up already sounded for this phase.
+    warned: bool,
+    /// When the alarm problem on the status line was shown, so it can go away again.
+    alarm_status_at: Option<Instant>,
+    /// Mode, resulting `seconds_left` and elapsed seconds of the last paused length edit.
+    length_edit: Option<(Mode, i32, i32)>,
+    /// Stopwatch time from earlier runs, and when the current run started.
+    stopwatch_banked: Duration,
+    stopwatch_since: Option<Instant>,
 }
 
-fn load_config() -> AppConfig {
-    fs::read_to_string("config.json")
-        .and_then(|data| Ok(serde_json::from_str(&data).unwrap_or_default()))
-        .unwrap_or_default()
+/// The phase position from just before a reset, kept for `UNDO_WINDOW` so a misclick can be undone.
+struct ResetUndo {
+    taken: Instant,
+    running: bool,
+    mode: Mode,
+    sessions_completed: i32,
+    focus_since_long_break: i32,
+    seconds_left: i32,
+    extra_seconds: i32,
+    resume_to: Option<Mode>,
+    paused_total: Duration,
+    stopwatch: Duration,
 }
 
-fn save_config(config: &AppConfig) {
-    if let Ok(json) = serde_json::to_string_pretty(config) {
-        let _ = fs::write("config.json", json);
+/// How long the Undo button stays up after a reset.
+const UNDO_WINDOW: Duration = Duration::from_secs(6);
+
+impl AppState {
+    fn new(file: ConfigFile, stats: Stats) -> Self {
+        Self::with_output(file, stats, OutputStream::try_default().ok())
     }
-}
 
-fn play_alarm(path: String) {
-    thread::spawn(move || {
-        let (_stream, stream_handle) = match OutputStream::try_default() {
-            Ok(s) => s,
-            Err(_) => return,
+    /// `new` with the audio output given rather than opened, so tests can run without one.
+    fn with_output(file: ConfigFile, stats: Stats, output: Option<(OutputStream, OutputStreamHandle)>) -> Self {
+        let config = file.active().clone();
+        Self {
+            seconds_left: config.phase_seconds(Mode::Work),
+            mode: Mode::Work,
+            sessions_completed: 0,
+            focus_since_long_break: 0,
+            alarm_cache: Vec::new(),
+            output,
+            dnd_active: false,
+            auto_paused: false,
+            rendered_second: None,
+            rendered_pause: None,
+            log_forced: false,
+            extra_seconds: 0,
+            just_ended: None,
+            resume_to: None,
+            snoozes: 0,
+            focus_without_break: 0,
+            break_reminders: 0,
+            status_server: None,
+            command_socket: None,
+            last_toggle: None,
+            tick: None,
+            ambient: None,
+            warned: false,
+            undo_reset: None,
+            alarm_status_at: None,
+            length_edit: None,
+            stopwatch_banked: Duration::ZERO,
+            stopwatch_since: None,
+            config,
+            stats,
+            deadline: Instant::now(),
+            paused_at: None,
+            accumulated_pause: Duration::ZERO,
+            alarm: AlarmSlot::default(),
+            saved_durations: None,
+            profiles: file.profiles,
+            active_profile: file.active_profile,
+        }
+    }
+
+    fn log_event(&self, event: &str) {
+        if !(self.config.event_log || self.log_forced) { return; }
+        let now = Local::now();
+        let line = serde_json::json!({
+            "ts": now.to_rfc3339(),
+            "event": event,
+            "mode": mode_key(self.mode),
+            "seconds_left": self.seconds_left,
+            "sessions": self.sessions_completed,
+        });
+        let _ = event_log_writer().send((now.date_naive(), line.to_string()));
+    }
+
+    fn paused_total(&self) -> Duration {
+        self.accumulated_pause + self.paused_at.map(|t| t.elapsed()).unwrap_or_default()
+    }
+
+    /// The config as it should be written to disk: CLI duration overrides stay
+    /// session-only until the user edits the durations in the UI.
+    fn config_to_save(&self) -> AppConfig {
+        let mut config = self.config.clone();
+        if let Some(durations) = self.saved_durations {
+            config.set_durations(durations);
+        }
+        config
+    }
+
+    fn config_file(&self) -> ConfigFile {
+        let mut profiles = self.profiles.clone();
+        if let Some(p) = profiles.iter_mut().find(|p| p.name == self.active_profile) {
+            p.config = self.config_to_save();
+        }
+        ConfigFile { active_profile: self.active_profile.clone(), profiles }
+    }
+
+    fn persist_config(&self) {
+        save_config(&self.config_file());
+    }
+
+    /// Takes over an imported settings file wholesale. Like a profile switch, a running
+    /// phase keeps its countdown.
+    fn replace_config(&mut self, file: ConfigFile, running: bool) {
+        self.config = file.active().clone();
+        self.active_profile = file.active_profile;
+        self.profiles = file.profiles;
+        self.saved_durations = None;
+        self.cache_alarms();
+        self.sync_dnd(running);
+        self.sync_status_server();
+        self.sync_command_socket();
+        if !running {
+            self.seconds_left = self.phase_seconds(self.mode);
+        }
+        self.persist_config();
+    }
+
+    /// Makes the profile the last session ran under active again, so the restored phase
+    /// gets its durations. One deleted since then falls back to the default, with a warning.
+    fn restore_profile(&mut self, name: &str) {
+        let target = if self.profiles.iter().any(|p| p.name == name) {
+            name.to_string()
+        } else {
+            // Deleted since: the default profile, or the first one if that's gone too.
+            let fallback = self.profiles.iter().find(|p| p.name == DEFAULT_PROFILE).unwrap_or(&self.profiles[0]).name.clone();
+            eprintln!("warning: profile \"{name}\" from the last session no longer exists; using \"{fallback}\"");
+            fallback
+        };
+        if target != self.active_profile && self.switch_profile(&target, false) {
+            self.cache_alarms();
+            self.persist_config();
+        }
+    }
+
+    /// Switches the active profile, keeping the outgoing profile's edits. A running
+    /// phase keeps its countdown; the new durations apply from the next phase.
+    fn switch_profile(&mut self, name: &str, running: bool) -> bool {
+        let Some(next) = self.profiles.iter().find(|p| p.name == name).map(|p| p.config.clone()) else { return false };
+        let current = self.config_to_save();
+        if let Some(p) = self.profiles.iter_mut().find(|p| p.name == self.active_profile) {
+            p.config = current;
+        }
+        self.active_profile = name.to_string();
+        self.config = next;
+        self.saved_durations = None;
+        self.sync_status_server();
+        self.sync_command_socket();
+        if !running {
+            self.seconds_left = self.phase_seconds(self.mode);
+        }
+        true
+    }
+
+    /// Keeps exactly the alarms the current config can play decoded, e.g. after a pick,
+    /// a profile switch or a new max alarm length, so playback later starts without disk I/O.
+    fn cache_alarms(&mut self) {
+        let mut wanted = [AlarmTarget::Work, AlarmTarget::Break, AlarmTarget::Cycle].map(|t| self.config.effective_alarm(t)).to_vec();
+        if self.config.warn_before_seconds > 0 { wanted.push(self.config.effective_alarm(AlarmTarget::Warning)); }
+        let limit = self.config.alarm_buffer_limit();
+        self.alarm_cache.retain(|a| a.limit == limit && wanted.contains(&a.path.as_str()));
+        for path in wanted {
+            if !self.alarm_cache.iter().any(|a| a.path == path) {
+                self.alarm_cache.push(cache_alarm(path, limit));
+            }
+        }
+    }
+
+    fn cached_alarm(&mut self, path: &str) -> &CachedAlarm {
+        let limit = self.config.alarm_buffer_limit();
+        if let Some(i) = self.alarm_cache.iter().position(|a| a.path == path && a.limit == limit) {
+            return &self.alarm_cache[i];
+        }
+        self.alarm_cache.retain(|a| a.path != path);
+        self.alarm_cache.push(cache_alarm(path, limit));
+        self.alarm_cache.last().unwrap()
+    }
+
+    fn audio_available(&self) -> bool {
+        self.output.is_some()
+    }
+
+    /// Plays the alarm for the phase that is running out now, before `advance`.
+    fn play_alarm(&mut self) -> Option<String> {
+        let mut target = AlarmTarget::ending(self.mode, self.next_mode());
+        // A reopened phase already had its cycle celebrated when it first ended.
+        if target == AlarmTarget::Cycle && self.resume_to.is_some() { target = AlarmTarget::Work; }
+        let path = self.config.effective_alarm(target).to_string();
+        self.play_alarm_file(&path)
+    }
+
+    /// Returns what went wrong, if anything, for the status line.
+    fn play_alarm_file(&mut self, path: &str) -> Option<String> {
+        stop_alarm(&self.alarm);
+        // The tick and ambient loop come back by themselves once the alarm has finished.
+        self.tick = None;
+        self.ambient = None;
+        let cached = self.cached_alarm(path);
+        let sound = cached.sound.clone();
+        let fell_back = !path.is_empty() && cached.is_default;
+        // The device is opened once and kept; retry here in case it wasn't there at startup.
+        if self.output.is_none() {
+            match OutputStream::try_default() {
+                Ok(output) => self.output = Some(output),
+                Err(e) => return Some(format!("Alarm failed: no audio output ({e})")),
+            }
+        }
+        let (_, handle) = self.output.as_ref()?;
+        let sink = match Sink::try_new(handle) {
+            Ok(sink) => sink,
+            Err(e) => return Some(format!("Alarm failed: could not start playback ({e})")),
         };
-        let sink = match Sink::try_new(&stream_handle) {
-            Ok(s) => s,
-            Err(_) => return,
+        let style = self.config.alarm_style();
+        sink.set_volume(style.volume);
+        append_sound(&sink, sound, style);
+        *self.alarm.lock().unwrap() = Some(Arc::new(sink));
+        fell_back.then(|| format!("Alarm failed: could not open {path}, played the default bell"))
+    }
+
+    /// True once per phase, on the tick where `seconds_left` first reaches
+    /// `warn_before_seconds`. Extending back above it doesn't re-arm the chime.
+    fn warning_due(&mut self) -> bool {
+        let warn = self.config.warn_before_seconds;
+        if warn <= 0 || self.warned || self.seconds_left > warn || self.seconds_left <= 0 { return false; }
+        self.warned = true;
+        // A phase no longer than the heads-up would chime the moment it starts.
+        self.phase_total() > warn
+    }
+
+    /// The heads-up: one short, quieter play on a sink of its own, so it neither stops
+    /// nor waits for the tick or ambient loop.
+    fn play_warning(&mut self) {
+        let path = self.config.effective_alarm(AlarmTarget::Warning).to_string();
+        let sound = self.cached_alarm(&path).sound.clone();
+        if self.output.is_none() {
+            self.output = OutputStream::try_default().ok();
+        }
+        let Some((_, handle)) = &self.output else { return };
+        let Ok(sink) = Sink::try_new(handle) else { return };
+        let style = AlarmStyle {
+            volume: self.config.alarm_volume.clamp(0.0, 1.0) * WARNING_VOLUME,
+            looped: false,
+            fade: false,
+            max_duration: Some(WARNING_LENGTH),
         };
-        if let Ok(file) = fs::File::open(&path) {
-            if let Ok(source) = Decoder::new(BufReader::new(file)) {
-                sink.append(source);
-                sink.sleep_until_end();
+        sink.set_volume(style.volume);
+        append_sound(&sink, sound, style);
+        sink.detach();
+    }
+
+    fn stopwatch_elapsed(&self) -> Duration {
+        self.stopwatch_banked + self.stopwatch_since.map(|t| t.elapsed()).unwrap_or_default()
+    }
+
+    /// Ends a stopwatch session: its time is credited as focus and, from a whole minute
+    /// up, logged as a `stopwatch` row. The stopwatch goes back to zero.
+    fn stop_stopwatch(&mut self) -> Option<HistoryEntry> {
+        let seconds = self.stopwatch_elapsed().as_secs() as i32;
+        self.stopwatch_banked = Duration::ZERO;
+        self.stopwatch_since = None;
+        self.log_event("stop");
+        if seconds == 0 { return None; }
+        self.credit_focus(seconds);
+        save_stats(&self.stats);
+        (seconds >= 60).then(|| append_session_log(&self.config.language, STOPWATCH_LOG_KEY, seconds))
+    }
+
+    /// The big label over the timer.
+    fn phase_label(&self) -> &'static str {
+        match self.config.timer_kind {
+            TimerKind::Stopwatch => tr(&self.config.language, "mode.stopwatch"),
+            TimerKind::Pomodoro => mode_label(&self.config.language, self.mode),
+        }
+    }
+
+    fn credit_focus(&mut self, seconds: i32) {
+        let today = Local::now().date_naive();
+        self.stats.roll_day(today);
+        self.stats.total_focus_seconds += seconds as i64;
+        self.stats.today_focus_seconds += seconds;
+        self.stats.record_active(today);
+        self.focus_since_long_break += seconds;
+    }
+
+    fn credit_break(&mut self, seconds: i32) {
+        let today = Local::now().date_naive();
+        self.stats.roll_day(today);
+        self.stats.total_break_seconds += seconds as i64;
+        self.stats.today_break_seconds += seconds;
+        self.stats.record_active(today);
+    }
+
+    /// Length of the current phase including any minutes added with `extend`.
+    fn phase_total(&self) -> i32 {
+        self.phase_seconds(self.mode) + self.extra_seconds
+    }
+
+    /// Adds `minutes` to the phase. Shortly after a phase has ended (before the next
+    /// one was started by hand or got a minute in), it instead goes back to the
+    /// ended phase for `minutes`; the phase that was up next follows afterwards.
+    /// Returns true in that case, since the mode changed.
+    fn extend(&mut self, minutes: i32) -> bool {
+        if self.reopen(minutes) { return true; }
+        let add = minutes.max(1) * 60;
+        self.seconds_left += add;
+        self.extra_seconds += add;
+        self.deadline += Duration::from_secs(add as u64);
+        false
+    }
+
+    fn reopen(&mut self, minutes: i32) -> bool {
+        let elapsed = self.phase_total() - self.seconds_left;
+        let Some(ended) = self.just_ended.take().filter(|_| elapsed <= EXTEND_GRACE_SECONDS) else { return false };
+        let length = minutes.max(1) * 60;
+        self.resume_to = Some(self.mode);
+        self.mode = ended;
+        self.warned = false;
+        self.seconds_left = length;
+        self.extra_seconds = length - self.phase_seconds(ended);
+        self.restart_deadline();
+        true
+    }
+
+    /// Reopens the phase that just ended for `snooze_m` minutes, after which the
+    /// alarm fires again. Returns false when there's nothing to snooze or the
+    /// `max_snoozes` limit is reached.
+    fn snooze(&mut self) -> bool {
+        let limit = self.config.max_snoozes;
+        if limit > 0 && self.snoozes >= limit { return false; }
+        if !self.reopen(self.config.snooze_m) { return false; }
+        self.snoozes += 1;
+        true
+    }
+
+    fn long_break_due(&self, sessions: i32, focus_seconds: i32) -> bool {
+        if !self.config.long_break_enabled { return false; }
+        match self.config.schedule_mode {
+            ScheduleMode::SessionCount => sessions % self.config.long_break_interval() == 0,
+            ScheduleMode::FocusMinutes => focus_seconds >= self.config.long_break_after_seconds(),
+        }
+    }
+
+    /// The phase `advance` would move to if the current one ran to completion.
+    fn next_mode(&self) -> Mode {
+        if let Some(resume_to) = self.resume_to {
+            return resume_to;
+        }
+        match self.mode {
+            Mode::Work => {
+                let focus = self.focus_since_long_break + self.phase_total();
+                if self.long_break_due(self.sessions_completed + 1, focus) { Mode::LongBreak } else { Mode::ShortBreak }
             }
+            _ => Mode::Work,
         }
-    });
+    }
+
+    fn phase_seconds(&self, mode: Mode) -> i32 {
+        self.config.phase_seconds(mode)
+    }
+
+    /// Seconds the current phase has run. Right after a paused length edit that's the
+    /// figure the edit kept, since each keystroke is an edit and one on the way ("3" of
+    /// "30") may have clamped `seconds_left` to a second. Any other change to the phase
+    /// or to `seconds_left` drops back to working it out.
+    fn phase_elapsed(&self) -> i32 {
+        match self.length_edit {
+            Some((mode, left, elapsed)) if mode == self.mode && left == self.seconds_left => elapsed,
+            _ => self.phase_total() - self.seconds_left,
+        }
+    }
+
+    /// After a settings change while paused, with `elapsed` from `phase_elapsed` before
+    /// it. Only a new length for the phase we're in changes the countdown, and a phase
+    /// paused partway keeps the time already spent: 10 minutes into a 25-minute focus,
+    /// a new 30-minute length leaves 20 to go. A length shorter than that leaves one
+    /// second, so the phase ends on resume rather than going negative. Returns whether
+    /// `seconds_left` changed.
+    fn refit_paused_phase(&mut self, phase_before: i32, elapsed: i32) -> bool {
+        if self.phase_seconds(self.mode) == phase_before { return false; }
+        self.seconds_left = (self.phase_total() - elapsed).max(1);
+        self.length_edit = Some((self.mode, self.seconds_left, elapsed));
+        true
+    }
+
+    fn restart_deadline(&mut self) {
+        self.deadline = Instant::now() + Duration::from_secs(self.seconds_left.max(0) as u64);
+    }
+
+    /// Starts, stops or moves the status server to match the config.
+    fn sync_status_server(&mut self) {
+        let want = self.config.status_server.then_some(self.config.status_port);
+        if self.status_server.as_ref().map(|server| server.port) != want {
+            // Drop the old server first so a restart on the same port can bind.
+            self.status_server = None;
+            self.status_server = want.and_then(StatusServer::start);
+        }
+    }
+
+    fn sync_command_socket(&mut self) {
+        let want = self.config.command_socket.then(|| command_socket_path(&self.config.command_socket_path));
+        if self.command_socket.as_ref().map(|socket| &socket.path) != want.as_ref() {
+            self.command_socket = None;
+            self.command_socket = want.and_then(CommandSocket::start);
+        }
+    }
+
+    fn publish_status(&self, running: bool) {
+        let Some(server) = &self.status_server else { return };
+        *server.snapshot.lock().unwrap() = StatusSnapshot {
+            mode: mode_key(self.mode),
+            seconds_left: self.seconds_left,
+            is_running: running,
+            sessions_completed: self.sessions_completed,
+        };
+    }
+
+    /// Whether today's count has just reached the daily goal, at most once a day. Raising the
+    /// goal past today's count re-arms it, so the new target gets celebrated too.
+    fn goal_just_reached(&mut self) -> bool {
+        let goal = self.config.daily_goal;
+        if goal <= 0 || self.stats.today_sessions < goal {
+            self.stats.goal_celebrated = false;
+            return false;
+        }
+        if self.stats.goal_celebrated { return false; }
+        self.stats.goal_celebrated = true;
+        save_stats(&self.stats);
+        true
+    }
+
+    /// The summary line for notifications: the app title, plus the active profile when
+    /// there's more than one, so instances and profiles can be told apart.
+    fn notification_title(&self) -> String {
+        let title = message_or(&self.config.app_title, DEFAULT_APP_TITLE).trim();
+        if self.profiles.len() > 1 { format!("{title} · {}", self.active_profile) } else { title.to_string() }
+    }
+
+    /// Today's summary once it's due: at `day_summary_time`, or when `quitting` if no time
+    /// is set. At most once a day, and only after something was completed.
+    fn day_summary_due(&mut self, quitting: bool) -> Option<String> {
+        if !self.config.day_summary || self.stats.day_summary_shown || self.stats.today_sessions == 0 { return None; }
+        let due = match NaiveTime::parse_from_str(self.config.day_summary_time.trim(), "%H:%M") {
+            Ok(at) => Local::now().time() >= at,
+            Err(_) => quitting,
+        };
+        if !due { return None; }
+        self.stats.day_summary_shown = true;
+        save_stats(&self.stats);
+        let language = &self.config.language;
+        let goal = self.config.daily_goal;
+        let goal_text = match goal {
+            0 => "",
+            _ if self.stats.today_sessions >= goal => tr(language, "summary.goal_reached"),
+            _ => tr(language, "summary.goal_missed"),
+        };
+        Some(tr(language, "notice.summary")
+            .replace("{n}", &self.stats.today_sessions.to_string())
+            .replace("{t}", &format_duration(self.stats.today_focus_seconds))
+            + goal_text)
+    }
+
+    /// The milestone that the pomodoro just completed landed on, if it hasn't been celebrated yet.
+    /// Only the exact crossing counts: stats from before milestones existed don't fire late.
+    fn milestone_just_reached(&mut self) -> Option<i32> {
+        let total = self.stats.total_sessions;
+        if !MILESTONES.contains(&total) || self.stats.milestones_celebrated.contains(&total) { return None; }
+        self.stats.milestones_celebrated.push(total);
+        save_stats(&self.stats);
+        Some(total)
+    }
+
+    /// A reminder once focus has gone `break_reminder_m` without a break, and again every
+    /// `BREAK_REMINDER_REPEAT` after that, worded more firmly each time.
+    fn break_reminder_due(&mut self) -> Option<String> {
+        let cap = self.config.break_reminder_m * 60;
+        if cap <= 0 { return None; }
+        let current = match self.config.timer_kind {
+            TimerKind::Stopwatch => self.stopwatch_elapsed().as_secs() as i32,
+            TimerKind::Pomodoro if self.mode == Mode::Work => self.phase_total() - self.seconds_left,
+            TimerKind::Pomodoro => 0,
+        };
+        let total = self.focus_without_break + current;
+        if total < cap + self.break_reminders * BREAK_REMINDER_REPEAT { return None; }
+        self.break_reminders += 1;
+        let key = if self.break_reminders == 1 { "notice.overrun" } else { "notice.overrun_again" };
+        Some(tr(&self.config.language, key).replace("{t}", &format_duration(total)))
+    }
+
+    /// Strict mode only forbids pausing focus; breaks can always be paused.
+    fn pause_locked(&self) -> bool {
+        self.config.strict_mode && self.mode == Mode::Work
+    }
+
+    /// Keeps the system in Do Not Disturb exactly while a work phase is running.
+    fn sync_dnd(&mut self, running: bool) {
+        let want = self.config.manage_dnd && running && self.mode == Mode::Work;
+        if want != self.dnd_active {
+            set_dnd(want);
+            self.dnd_active = want;
+        }
+    }
+
+    /// Plays the focus tick exactly while a work phase runs and no alarm is sounding.
+    fn sync_tick(&mut self, running: bool) {
+        let alarm_playing = self.alarm.lock().unwrap().as_ref().is_some_and(|sink| !sink.empty());
+        let want = self.config.focus_tick && running && self.mode == Mode::Work && !alarm_playing;
+        if want == self.tick.is_some() { return; }
+        if !want {
+            self.tick = None;
+            return;
+        }
+        if self.output.is_none() {
+            self.output = OutputStream::try_default().ok();
+        }
+        let Some((_, handle)) = &self.output else { return };
+        let Ok(sink) = Sink::try_new(handle) else { return };
+        sink.set_volume(self.config.focus_tick_volume);
+        sink.append(TickSound::default());
+        self.tick = Some(sink);
+    }
+
+    /// Loops the ambient sound while focus runs; like the tick, it makes way for the alarm.
+    fn sync_ambient(&mut self, running: bool) {
+        let alarm_playing = self.alarm.lock().unwrap().as_ref().is_some_and(|sink| !sink.empty());
+        let path = &self.config.ambient_path;
+        let want = !path.is_empty() && running && self.mode == Mode::Work && !alarm_playing;
+        // A profile switch or import can change the file mid-phase.
+        if want && self.ambient.as_ref().is_some_and(|(playing, _)| playing == path) { return; }
+        self.ambient = None;
+        if !want { return; }
+        let path = path.clone();
+        // A file that no longer opens or decodes just leaves the background quiet.
+        let Some(sound) = AmbientLoop::open(Path::new(&path)) else {
+            self.ambient = Some((path, None));
+            return;
+        };
+        if self.output.is_none() {
+            self.output = OutputStream::try_default().ok();
+        }
+        let Some((_, handle)) = &self.output else { return };
+        let Ok(sink) = Sink::try_new(handle) else { return };
+        sink.set_volume(self.config.ambient_volume);
+        sink.append(sound.fade_in(ALARM_FADE_IN));
+        self.ambient = Some((path, Some(sink)));
+    }
+
+    fn set_running(&mut self, running: bool) {
+        self.sync_dnd(running);
+        self.sync_tick(running);
+        self.sync_ambient(running);
+        self.auto_paused = false;
+        if running {
+            if let Some(t) = self.paused_at.take() { self.accumulated_pause += t.elapsed(); }
+            self.restart_deadline();
+            if self.config.timer_kind == TimerKind::Stopwatch { self.stopwatch_since.get_or_insert_with(Instant::now); }
+        } else {
+            if let Some(t) = self.stopwatch_since.take() { self.stopwatch_banked += t.elapsed(); }
+            if self.paused_at.is_none() { self.paused_at = Some(Instant::now()); }
+        }
+        self.save_session(running);
+    }
+
+    /// Writes the current position, or removes the file when there's nothing to resume.
+    fn save_session(&self, running: bool) {
+        let stopwatch = self.stopwatch_elapsed();
+        let untouched = self.sessions_completed == 0 && self.mode == Mode::Work && self.seconds_left == self.phase_seconds(Mode::Work) && stopwatch.is_zero();
+        if !running && untouched {
+            clear_session();
+            return;
+        }
+        let ends_at = running.then(|| Local::now() + chrono::Duration::seconds(self.tick_remaining() as i64));
+        let session = SessionState {
+            mode: self.mode,
+            sessions_completed: self.sessions_completed,
+            focus_since_long_break: self.focus_since_long_break,
+            seconds_left: self.seconds_left,
+            ends_at,
+            stopwatch_seconds: (!stopwatch.is_zero()).then_some(stopwatch.as_secs() as i32),
+            profile: Some(self.active_profile.clone()),
+        };
+        if let Ok(json) = serde_json::to_string_pretty(&session) {
+            let _ = fs::write(data_path(SESSION_STATE), json);
+        }
+    }
+
+    fn tick_remaining(&self) -> u64 {
+        self.deadline.saturating_duration_since(Instant::now()).as_secs()
+    }
+
+    /// Puts the timer back where `saved` left it and returns whether it should keep
+    /// running. A phase that ran out while the app was closed counts as completed.
+    fn restore_session(&mut self, saved: &SessionState) -> bool {
+        self.mode = saved.mode;
+        self.sessions_completed = saved.sessions_completed;
+        self.focus_since_long_break = saved.focus_since_long_break;
+        if let Some(seconds) = saved.stopwatch_seconds {
+            self.stopwatch_banked = Duration::from_secs(seconds.max(0) as u64);
+            return saved.ends_at.is_some();
+        }
+        let remaining = saved.remaining();
+        if remaining <= 0 {
+            self.seconds_left = 0;
+            self.advance(false);
+            return false;
+        }
+        self.seconds_left = (remaining as i32).min(self.phase_seconds(self.mode));
+        saved.ends_at.is_some()
+    }
+
+    /// Refreshes `seconds_left` from the deadline; returns true once the phase has run out.
+    /// Rounds up so a fresh phase shows its full length and 00:00 only at expiry.
+    fn tick(&mut self) -> bool {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        self.seconds_left = remaining.as_millis().div_ceil(1000) as i32;
+        remaining.is_zero()
+    }
+
+    /// Moves to the next phase and re-arms the deadline. A skipped work phase earns
+    /// no focus time and no log entry. Unless `count_skipped_work` is on, it also
+    /// doesn't count toward the sessions or the goal, so it leads into a short break
+    /// and stays quiet; counted, it can bring the long break and a goal or milestone
+    /// notification like a finished one.
+    fn advance(&mut self, skipped: bool) -> Transition {
+        self.log_event(if skipped { "skip" } else { "complete" });
+        let ended = self.mode;
+        let elapsed = self.phase_total() - self.seconds_left;
+        if ended == Mode::Work {
+            self.focus_without_break += elapsed;
+        } else if !skipped {
+            self.focus_without_break = 0;
+            self.break_reminders = 0;
+        }
+        // Focus time is only credited for a phase that ran out; the pomodoro itself may count either way.
+        let counted = ended == Mode::Work && self.resume_to.is_none() && (!skipped || self.config.count_skipped_work);
+        let mut cycle_complete = false;
+        let mut logged = None;
+        if self.resume_to.is_none() { self.snoozes = 0; }
+        if let Some(resume_to) = self.resume_to.take() {
+            // The phase was already credited when it first ended; only the extra time counts.
+            if !skipped {
+                if ended == Mode::Work { self.credit_focus(self.phase_total()); } else { self.credit_break(self.phase_total()); }
+                save_stats(&self.stats);
+            }
+            self.mode = resume_to;
+        } else {
+            match self.mode {
+                Mode::Work => {
+                    if !skipped {
+                        self.credit_focus(self.phase_total());
+                        logged = Some(append_session_log(&self.config.language, mode_key(Mode::Work), self.phase_total()));
+                    }
+                    if counted {
+                        self.sessions_completed += 1;
+                        self.stats.total_sessions += 1;
+                        self.stats.today_sessions += 1;
+                        save_stats(&self.stats);
+                    }
+                    if counted && self.long_break_due(self.sessions_completed, self.focus_since_long_break) {
+                        self.mode = Mode::LongBreak;
+                        self.focus_since_long_break = 0;
+                        cycle_complete = true;
+                        self.stats.total_cycles += 1;
+                        self.stats.today_cycles += 1;
+                        save_stats(&self.stats);
+                    } else {
+                        self.mode = Mode::ShortBreak;
+                    }
+                }
+                _ => {
+                    if !skipped {
+                        self.credit_break(self.phase_total());
+                        save_stats(&self.stats);
+                    }
+                    self.mode = Mode::Work;
+                }
+            }
+        }
+        let milestone = if counted { self.milestone_just_reached() } else { None };
+        let goal_reached = counted && self.goal_just_reached();
+        let language = &self.config.language;
+        // A counted skip still gets its goal or milestone; otherwise skipping stays quiet.
+        let notice = match ended {
+            Mode::Work if milestone.is_some() => Some(tr(language, "notice.milestone").replace("{n}", &self.stats.total_sessions.to_string())),
+            Mode::Work if goal_reached => Some(tr(language, "notice.goal").replace("{n}", &self.stats.today_sessions.to_string())),
+            _ if skipped => None,
+            Mode::Work if cycle_complete => Some(tr(language, "notice.cycle").to_string()),
+            Mode::Work => Some(message_or(&self.config.work_done_message, tr(language, "notice.work_done")).to_string()),
+            _ => Some(message_or(&self.config.break_done_message, tr(language, "notice.break_done")).to_string()),
+        };
+        self.extra_seconds = 0;
+        self.warned = false;
+        self.just_ended = (!skipped).then_some(ended);
+        let auto_start = if self.mode == Mode::Work { self.config.auto_start_work } else { self.config.auto_start_breaks };
+        self.seconds_left = self.phase_seconds(self.mode);
+        // A throttled timer (minimized or occluded window) can notice the end a few
+        // seconds late; an auto-started phase absorbs that delay so the schedule
+        // doesn't drift. Anything longer (system sleep) just re-arms from now, and
+        // either way the phase ends, and the alarm fires, exactly once per tick.
+        let late = Instant::now().saturating_duration_since(self.deadline);
+        if !skipped && auto_start && late < CATCH_UP_LIMIT {
+            self.seconds_left -= (late.as_secs() as i32).min(self.seconds_left - 1);
+        }
+        self.restart_deadline();
+        let url = self.config.webhook_url.trim();
+        if !url.is_empty() {
+            let event = match (ended, skipped) {
+                (Mode::Work, false) => "work_complete",
+                (Mode::Work, true) => "work_skipped",
+                (_, false) => "break_complete",
+                (_, true) => "break_skipped",
+            };
+            post_webhook(url.to_string(), serde_json::json!({
+                "event": event,
+                "sessions": self.sessions_completed,
+                "ended": mode_key(ended),
+                "entering": mode_key(self.mode),
+            }));
+        }
+        Transition {
+            auto_start,
+            logged,
+            // Without a sound device the notification is the only alarm left, so it's sent regardless.
+            notice: notice.filter(|_| self.config.notifications_enabled || !self.audio_available()),
+        }
+    }
+}
+
+/// Directory holding config, stats and the session log: `POMODORO_DATA_DIR` if set,
+/// otherwise the platform config dir, so the launch directory doesn't matter.
+fn data_dir() -> &'static Path {
+    static DIR: OnceLock<PathBuf> = OnceLock::new();
+    DIR.get_or_init(|| {
+        let dir = std::env::var_os("POMODORO_DATA_DIR")
+            .filter(|d| !d.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| ProjectDirs::from("", "", "pomodoro_timer").map(|d| d.config_dir().to_path_buf()))
+            .unwrap_or_else(|| PathBuf::from("."));
+        if let Err(e) = fs::create_dir_all(&dir) {
+            eprintln!("warning: could not create data dir {} ({e})", dir.display());
+        }
+        dir
+    })
+}
+
+fn data_path(name: &str) -> PathBuf {
+    data_dir().join(name)
+}
+
+/// Where settings go once `config.json` in the data dir can't be written: the first
+/// platform dir that isn't the data dir itself, with the temp dir as a last resort.
+fn fallback_config_path() -> PathBuf {
+    let dirs = ProjectDirs::from("", "", "pomodoro_timer");
+    dirs.iter()
+        .flat_map(|d| [d.config_dir().to_path_buf(), d.data_local_dir().to_path_buf()])
+        .chain([std::env::temp_dir().join("pomodoro_timer")])
+        .find(|dir| dir != data_dir())
+        .unwrap_or_else(|| std::env::temp_dir().join("pomodoro_timer"))
+        .join("config.json")
+}
+
+/// First save failure of this run, waiting for the window to show it.
+static SAVE_WARNING: Mutex<Option<String>> = Mutex::new(None);
+static SAVE_FAILED: AtomicBool = AtomicBool::new(false);
+
+fn take_save_warning() -> Option<String> {
+    SAVE_WARNING.lock().ok()?.take()
+}
+
+fn load_config() -> ConfigFile {
+    let mut path = data_path("config.json");
+    // Settings end up in the fallback while config.json is unwritable; a newer fallback wins.
+    let fallback = fallback_config_path();
+    let modified = |p: &Path| fs::metadata(p).ok().filter(|m| m.is_file()).and_then(|m| m.modified().ok());
+    if let Some(saved) = modified(&fallback) {
+        if modified(&path).is_none_or(|primary| saved > primary) {
+            path = fallback;
+        }
+    }
+    match fs::read_to_string(&path) {
+        Ok(data) => ConfigFile::parse(&data).unwrap_or_else(|e| {
+            eprintln!("warning: {} is invalid ({e}); using defaults, the file will be backed up to config.json.bak on next save", path.display());
+            ConfigFile::default()
+        }),
+        Err(e) => {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                eprintln!("warning: could not read {} ({e}); using defaults", path.display());
+            }
+            ConfigFile::default()
+        }
+    }
+}
+
+fn save_config(file: &ConfigFile) {
+    // Keep a hand-edited file that failed to parse instead of silently replacing it.
+    let path = data_path("config.json");
+    if let Ok(existing) = fs::read_to_string(&path) {
+        if ConfigFile::parse(&existing).is_err() {
+            let _ = fs::write(data_path("config.json.bak"), existing);
+        }
+    }
+    let Ok(json) = serde_json::to_string_pretty(file) else { return };
+    let Err(e) = fs::write(&path, &json) else { return };
+    let fallback = fallback_config_path();
+    let saved = fallback.parent().is_some_and(|dir| fs::create_dir_all(dir).is_ok()) && fs::write(&fallback, &json).is_ok();
+    // Every later save would fail the same way, so only the first one is reported.
+    if SAVE_FAILED.swap(true, Ordering::Relaxed) { return; }
+    eprintln!("warning: could not write {} ({e})", path.display());
+    let warning = if saved {
+        eprintln!("warning: settings are being saved to {} instead", fallback.display());
+        format!("Couldn't save settings to {} ({e}); using {} instead", path.display(), fallback.display())
+    } else {
+        format!("Couldn't save settings to {} ({e}); changes won't be kept after quitting", path.display())
+    };
+    if let Ok(mut slot) = SAVE_WARNING.lock() { *slot = Some(warning); }
+}
+
+const DEFAULT_ALARM: &[u8] = include_bytes!("../assets/alarm.wav");
+
+const ALARM_FADE_IN: Duration = Duration::from_secs(2);
+
+type AlarmSound = Buffered<Box<dyn Source<Item = i16> + Send>>;
+
+/// An alarm decoded once and kept in memory; clones of `sound` share the decoded
+/// frames, so every play after the first is just a buffer replay.
+struct CachedAlarm {
+    path: String,
+    sound: AlarmSound,
+    is_default: bool,
+    /// How much of the file `sound` holds; `None` for all of it.
+    limit: Option<Duration>,
+}
+
+/// Reads and decodes a user-picked alarm; `None` if it's missing or rodio can't decode it.
+fn load_alarm_file(path: &str, limit: Option<Duration>) -> Option<CachedAlarm> {
+    let bytes = fs::read(path).ok()?;
+    let decoder = Decoder::new(Cursor::new(Cow::Owned(bytes))).ok()?;
+    Some(buffer_alarm(path, decoder, false, limit))
+}
+
+/// Container/codec named by the file's magic bytes, for the formats rodio is built with.
+fn audio_format(bytes: &[u8]) -> Option<&'static str> {
+    match bytes {
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => Some("WAV"),
+        [b'O', b'g', b'g', b'S', ..] => Some("OGG"),
+        [b'f', b'L', b'a', b'C', ..] => Some("FLAC"),
+        [b'I', b'D', b'3', ..] => Some("MP3"),
+        [0xFF, second, ..] if second & 0xE0 == 0xE0 => Some("MP3"),
+        _ => None,
+    }
+}
+
+/// The file name to show for a picked path, or the whole path when it has none (a root,
+/// or one ending in `..`). Non-UTF-8 bytes come out as U+FFFD rather than failing.
+fn display_name(path: &Path) -> String {
+    path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().into_owned()
+}
+
+/// Checks that a picked file decodes and returns its format, with its length when the
+/// header records it (WAV, FLAC); or says why it can't be used. Only the header is
+/// decoded, so a long file doesn't hold up the UI thread.
+fn probe_audio_file(path: &Path) -> Result<(&'static str, Option<Duration>), String> {
+    let name = display_name(path);
+    let read_error = |e: std::io::Error| format!("Can't read {name} ({e})");
+    let mut file = fs::File::open(path).map_err(read_error)?;
+    let mut head = Vec::new();
+    (&mut file).take(12).read_to_end(&mut head).map_err(read_error)?;
+    let Some(format) = audio_format(&head) else {
+        return Err(format!("{name} isn't WAV, OGG Vorbis, FLAC or MP3"));
+    };
+    file.seek(SeekFrom::Start(0)).map_err(read_error)?;
+    let decoder = Decoder::new(BufReader::new(file))
+        .map_err(|_| format!("Can't decode {name}; the {format} data isn't supported or is damaged"))?;
+    Ok((format, decoder.total_duration()))
+}
+
+/// The label for a probed file: "song.ogg (OGG, 3:12)", or without the length.
+fn audio_label(name: &str, format: &str, length: Option<Duration>) -> String {
+    match length {
+        Some(length) => format!("{name} ({format}, {})", clock_text(length.as_secs() as i64)),
+        None => format!("{name} ({format})"),
+    }
+}
+
+/// Shows a picked file's label, working out a length the header doesn't record (MP3,
+/// OGG) by decoding the file on a worker thread. The label is only updated if it still
+/// shows that file by then.
+fn show_audio_label(ui: &AppWindow, path: &Path, format: &'static str, length: Option<Duration>,
+                    get: fn(&AppWindow) -> SharedString, set: fn(&AppWindow, SharedString)) {
+    let name = display_name(path);
+    let shown: SharedString = audio_label(&name, format, length).into();
+    set(ui, shown.clone());
+    if length.is_some() { return; }
+    let path = path.to_path_buf();
+    let ui = ui.as_weak();
+    thread::spawn(move || {
+        let Some(decoder) = open_decoder(&path) else { return };
+        let samples_per_second = decoder.channels().max(1) as u64 * decoder.sample_rate().max(1) as u64;
+        let length = Duration::from_secs(decoder.count() as u64 / samples_per_second);
+        let _ = ui.upgrade_in_event_loop(move |ui| {
+            if get(&ui) == shown { set(&ui, audio_label(&name, format, Some(length)).into()); }
+        });
+    });
+}
+
+fn open_decoder(path: &Path) -> Option<Decoder<BufReader<fs::File>>> {
+    Decoder::new(BufReader::new(fs::File::open(path).ok()?)).ok()
+}
+
+/// Plays a file from disk over and over, reopening it at the end instead of keeping its
+/// decoded samples: an hour of ambient sound would be hundreds of MB of PCM, which is
+/// also what rodio's `repeat_infinite` would end up holding after the first pass.
+struct AmbientLoop {
+    path: PathBuf,
+    decoder: Decoder<BufReader<fs::File>>,
+}
+
+impl AmbientLoop {
+    fn open(path: &Path) -> Option<Self> {
+        Some(Self { path: path.to_path_buf(), decoder: open_decoder(path)? })
+    }
+}
+
+impl Iterator for AmbientLoop {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if let Some(sample) = self.decoder.next() { return Some(sample); }
+        // A file that's gone or empty by now ends the loop.
+        self.decoder = open_decoder(&self.path)?;
+        self.decoder.next()
+    }
+}
+
+impl Source for AmbientLoop {
+    fn current_frame_len(&self) -> Option<usize> { self.decoder.current_frame_len() }
+    fn channels(&self) -> u16 { self.decoder.channels() }
+    fn sample_rate(&self) -> u32 { self.decoder.sample_rate() }
+    fn total_duration(&self) -> Option<Duration> { None }
+}
+
+/// Loads `path`, falling back to the bundled bell when it's empty or can't be decoded.
+fn cache_alarm(path: &str, limit: Option<Duration>) -> CachedAlarm {
+    (!path.is_empty()).then(|| load_alarm_file(path, limit)).flatten().unwrap_or_else(|| {
+        let decoder = Decoder::new(Cursor::new(Cow::Borrowed(DEFAULT_ALARM))).expect("bundled alarm is a valid wav");
+        buffer_alarm(path, decoder, true, limit)
+    })
+}
+
+/// Keeps the first `limit` of the sound (all of it for `None`), as nothing plays past that.
+fn buffer_alarm(path: &str, decoder: Decoder<Cursor<Cow<'static, [u8]>>>, is_default: bool, limit: Option<Duration>) -> CachedAlarm {
+    let source: Box<dyn Source<Item = i16> + Send> = match limit {
+        Some(limit) => Box::new(decoder.take_duration(limit)),
+        None => Box::new(decoder),
+    };
+    let sound = source.buffered();
+    // Decode it up front, off the UI thread, so the first play doesn't pay for it.
+    let warm = sound.clone();
+    thread::spawn(move || warm.for_each(drop));
+    CachedAlarm { path: path.to_string(), sound, is_default, limit }
+}
+
+/// An endless click-per-second: a short decaying 1.2 kHz blip followed by silence.
+#[derive(Default)]
+struct TickSound {
+    sample: u32,
+}
+
+impl TickSound {
+    const RATE: u32 = 44_100;
+    const CLICK: u32 = Self::RATE / 60;
+}
+
+impl Iterator for TickSound {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let i = self.sample;
+        self.sample = (self.sample + 1) % Self::RATE;
+        if i >= Self::CLICK { return Some(0.0); }
+        let t = i as f32 / Self::RATE as f32;
+        let decay = 1.0 - i as f32 / Self::CLICK as f32;
+        Some((t * 1200.0 * std::f32::consts::TAU).sin() * decay * decay)
+    }
+}
+
+impl Source for TickSound {
+    fn current_frame_len(&self) -> Option<usize> { None }
+    fn channels(&self) -> u16 { 1 }
+    fn sample_rate(&self) -> u32 { Self::RATE }
+    fn total_duration(&self) -> Option<Duration> { None }
+}
+
+fn append_sound(sink: &Sink, sound: AlarmSound, style: AlarmStyle) {
+    let mut source: Box<dyn Source<Item = i16> + Send> = Box::new(sound);
+    // Cut before looping, so a looped song repeats its opening instead of playing through.
+    if let Some(max) = style.max_duration { source = Box::new(source.take_duration(max)); }
+    if style.looped { source = Box::new(source.repeat_infinite()); }
+    // Applied after looping so only the first play-through ramps up.
+    if style.fade { source = Box::new(source.fade_in(ALARM_FADE_IN)); }
+    sink.append(source);
+}
+
+fn streak_text(stats: &Stats) -> String {
+    match stats.current_streak(Local::now().date_naive()) {
+        0 => String::new(),
+        1 => "🔥 1 day streak".to_string(),
+        days => format!("🔥 {days} day streak"),
+    }
+}
+
+fn focus_ratio_text(stats: &Stats) -> String {
+    match stats.today_focus_ratio() {
+        Some(percent) => format!("{percent}% focus today"),
+        None => "No phases finished today".to_string(),
+    }
+}
+
+fn format_duration(seconds: i32) -> String {
+    let hours = seconds / 3600;
+    let minutes = seconds % 3600 / 60;
+    if hours > 0 { format!("{hours}h {minutes}m") } else { format!("{minutes}m") }
+}
+
+fn load_stats() -> Stats {
+    fs::read_to_string(data_path("stats.json"))
+        .map(|data| serde_json::from_str(&data).unwrap_or_default())
+        .unwrap_or_default()
+}
+
+fn save_stats(stats: &Stats) {
+    if let Ok(json) = serde_json::to_string_pretty(stats) {
+        let _ = fs::write(data_path("stats.json"), json);
+    }
+}
+
+/// A phase end noticed later than this is treated as a sleep/resume, not timer lag.
+const CATCH_UP_LIMIT: Duration = Duration::from_secs(60);
+
+/// How far into the next phase an ended phase can still be reopened by `extend`.
+const EXTEND_GRACE_SECONDS: i32 = 60;
+
+/// The heads-up plays at this share of the alarm volume, for at most `WARNING_LENGTH`.
+const WARNING_VOLUME: f32 = 0.5;
+const WARNING_LENGTH: Duration = Duration::from_secs(3);
+
+/// How long an alarm problem stays on the status line under the timer.
+const ALARM_STATUS_TIME: Duration = Duration::from_secs(10);
+
+/// Gap between break reminders once focus runs past `break_reminder_m`.
+const BREAK_REMINDER_REPEAT: i32 = 15 * 60;
+
+/// UI tick; short so the progress bar moves smoothly, see `on_tick`.
+const TICK: Duration = Duration::from_millis(100);
+
+const SESSION_STATE: &str = "session_state.json";
+
+fn load_session() -> Option<SessionState> {
+    fs::read_to_string(data_path(SESSION_STATE)).ok().and_then(|data| serde_json::from_str(&data).ok())
+}
+
+fn clear_session() {
+    let _ = fs::remove_file(data_path(SESSION_STATE));
+}
+
+fn session_label(mode: Mode) -> &'static str {
+    match mode {
+        Mode::Work => "focus session",
+        Mode::ShortBreak => "short break",
+        Mode::LongBreak => "long break",
+    }
+}
+
+/// Asks whether to pick up `saved`, by dialog or on the terminal for `--no-gui`.
+fn confirm_resume(saved: &SessionState, gui: bool) -> bool {
+    let question = match saved.stopwatch_seconds {
+        Some(seconds) => format!("Stopwatch at {} — resume?", clock_text(seconds as i64)),
+        None => {
+            let left = saved.remaining();
+            format!("You had {:02}:{:02} left in a {} — resume?", left / 60, left % 60, session_label(saved.mode))
+        }
+    };
+    if gui {
+        rfd::MessageDialog::new()
+            .set_title("Pomodoro")
+            .set_description(question)
+            .set_buttons(rfd::MessageButtons::YesNo)
+            .show()
+            == rfd::MessageDialogResult::Yes
+    } else {
+        print!("{question} [Y/n] ");
+        let _ = std::io::stdout().flush();
+        let mut answer = String::new();
+        let _ = std::io::stdin().read_line(&mut answer);
+        !answer.trim().eq_ignore_ascii_case("n")
+    }
+}
+
+/// Whether quitting may go ahead; asks first if that would cut a running focus phase short.
+/// Takes plain values rather than the state: the dialog runs a nested event loop whose
+/// timers borrow the state, so no borrow may be held across it.
+fn confirm_quit(enabled: bool, mode: Mode, running: bool) -> bool {
+    if !enabled || !running || mode != Mode::Work { return true; }
+    rfd::MessageDialog::new()
+        .set_title("Pomodoro")
+        .set_description("A focus session is still running. Quit anyway?")
+        .set_buttons(rfd::MessageButtons::YesNo)
+        .show()
+        == rfd::MessageDialogResult::Yes
+}
+
+/// Background appender for the event log, so file I/O never runs on the UI thread.
+/// Each line goes to `logs/events-YYYY-MM-DD.jsonl`, which rotates the file daily.
+fn event_log_writer() -> &'static mpsc::Sender<(NaiveDate, String)> {
+    static WRITER: OnceLock<mpsc::Sender<(NaiveDate, String)>> = OnceLock::new();
+    WRITER.get_or_init(|| {
+        let (tx, rx) = mpsc::channel::<(NaiveDate, String)>();
+        thread::spawn(move || {
+            let dir = data_path("logs");
+            let _ = fs::create_dir_all(&dir);
+            for (day, line) in rx {
+                let path = dir.join(format!("events-{}.jsonl", day.format("%Y-%m-%d")));
+                let written = fs::OpenOptions::new().create(true).append(true).open(&path)
+                    .and_then(|mut file| writeln!(file, "{line}"));
+                if let Err(e) = written {
+                    eprintln!("warning: could not write {} ({e})", path.display());
+                }
+            }
+        });
+        tx
+    })
+}
+
+/// Something another thread wants done to the timer. Only the UI thread may touch
+/// `AppState`, so these are queued and applied by `apply_command` on a Slint timer.
+#[derive(Clone, Copy)]
+enum Command {
+    /// Start or resume; ignored while already running.
+    Start,
+    /// Pause; ignored while already paused.
+    Pause,
+    Toggle,
+    Reset,
+    Skip,
+}
+
+fn command_channel() -> &'static OnceLock<mpsc::Sender<Command>> {
+    static COMMANDS: OnceLock<mpsc::Sender<Command>> = OnceLock::new();
+    &COMMANDS
+}
+
+/// Queues `command` for the UI thread. `false` when no window is draining the queue
+/// (the `--no-gui` mode), in which case it's dropped.
+fn send_command(command: Command) -> bool {
+    command_channel().get().is_some_and(|tx| tx.send(command).is_ok())
+}
+
+/// Runs a queued command through the same callbacks the buttons use.
+fn apply_command(ui: &AppWindow, command: Command) {
+    match command {
+        Command::Start if !ui.get_is_running() => ui.invoke_toggle_timer(),
+        Command::Pause if ui.get_is_running() => ui.invoke_toggle_timer(),
+        Command::Start | Command::Pause => {}
+        Command::Toggle => ui.invoke_toggle_timer(),
+        Command::Reset => ui.invoke_reset_timer(),
+        Command::Skip => ui.invoke_skip_phase(),
+    }
+}
+
+/// What the command palette understands, with each command's argument for the hints.
+const PALETTE_COMMANDS: &[(&str, &str)] = &[
+    ("start", ""),
+    ("pause", ""),
+    ("toggle", ""),
+    ("skip", ""),
+    ("reset", ""),
+    ("undo", ""),
+    ("extend", "<min>"),
+    ("work", "<min>"),
+    ("short", "<min>"),
+    ("long", "<min>"),
+    ("profile", "<name>"),
+];
+
+/// A parsed palette line, ready to hand to the existing callbacks.
+enum PaletteCommand {
+    Run(Command),
+    Undo,
+    Extend(i32),
+    /// New length in minutes for the phase; clears an exact-seconds length.
+    Length(Mode, i32),
+    Profile(String),
+}
+
+/// Parses a palette line such as `work 45`, `skip` or `profile study`. Command words
+/// are case-insensitive and so are profile names, which come back as stored.
+fn parse_palette(input: &str, profiles: &[String]) -> Result<PaletteCommand, String> {
+    let input = input.trim();
+    let (word, arg) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+    let arg = arg.trim();
+    let minutes = |min: i32| -> Result<i32, String> {
+        match arg.parse::<i32>() {
+            Ok(m) if (min..=MAX_MINUTES).contains(&m) => Ok(m),
+            Ok(_) => Err(format!("{word} takes {min}-{MAX_MINUTES} minutes")),
+            Err(_) => Err(format!("Usage: {word} <min>")),
+        }
+    };
+    let command = match word.to_lowercase().as_str() {
+        "" => return Err("Type a command".to_string()),
+        "start" => PaletteCommand::Run(Command::Start),
+        "pause" => PaletteCommand::Run(Command::Pause),
+        "toggle" => PaletteCommand::Run(Command::Toggle),
+        "skip" => PaletteCommand::Run(Command::Skip),
+        "reset" => PaletteCommand::Run(Command::Reset),
+        "undo" => PaletteCommand::Undo,
+        "extend" => PaletteCommand::Extend(minutes(1)?),
+        "work" => PaletteCommand::Length(Mode::Work, minutes(MIN_MINUTES)?),
+        "short" => PaletteCommand::Length(Mode::ShortBreak, minutes(MIN_MINUTES)?),
+        "long" => PaletteCommand::Length(Mode::LongBreak, minutes(MIN_MINUTES)?),
+        "profile" => {
+            if arg.is_empty() { return Err("Usage: profile <name>".to_string()); }
+            let name = profiles.iter().find(|p| p.eq_ignore_ascii_case(arg)).ok_or_else(|| format!("No profile \"{arg}\""))?;
+            PaletteCommand::Profile(name.clone())
+        }
+        _ => return Err(format!("Unknown command \"{word}\"")),
+    };
+    Ok(command)
+}
+
+/// The hint line under the palette: the commands matching the typed word, or once an
+/// argument is being typed, that command's usage (profile names for `profile`).
+fn palette_hint(input: &str, profiles: &[String]) -> String {
+    let input = input.trim_start().to_lowercase();
+    let (word, arg) = match input.split_once(char::is_whitespace) {
+        Some((word, arg)) => (word, Some(arg.trim())),
+        None => (input.as_str(), None),
+    };
+    let usage = |(name, arg): &(&str, &str)| if arg.is_empty() { name.to_string() } else { format!("{name} {arg}") };
+    match arg {
+        Some(arg) if word == "profile" => {
+            let names: Vec<&str> = profiles.iter().filter(|p| p.to_lowercase().starts_with(arg)).map(String::as_str).collect();
+            if names.is_empty() { "No matching profile".to_string() } else { names.join(" · ") }
+        }
+        Some(_) => PALETTE_COMMANDS.iter().find(|(name, _)| *name == word).map(usage).unwrap_or_else(|| "Unknown command".to_string()),
+        None => {
+            let matches: Vec<String> = PALETTE_COMMANDS.iter().filter(|(name, _)| name.starts_with(word)).map(usage).collect();
+            if matches.is_empty() { "Unknown command".to_string() } else { matches.join(" · ") }
+        }
+    }
+}
+
+/// Runs a palette command through the same callbacks the buttons and settings use.
+/// Must be called without a borrow of the state, as those callbacks take their own.
+fn run_palette_command(ui: &AppWindow, command: PaletteCommand) -> Result<(), String> {
+    match command {
+        PaletteCommand::Run(command) => apply_command(ui, command),
+        PaletteCommand::Undo => ui.invoke_undo_reset(),
+        PaletteCommand::Extend(minutes) => ui.invoke_extend(minutes),
+        PaletteCommand::Length(mode, minutes) => {
+            let minutes = minutes.to_string().into();
+            match mode {
+                Mode::Work => { ui.set_work_setting(minutes); ui.set_work_seconds_setting("".into()); }
+                Mode::ShortBreak => { ui.set_short_break_setting(minutes); ui.set_short_seconds_setting("".into()); }
+                Mode::LongBreak => { ui.set_long_break_setting(minutes); ui.set_long_seconds_setting("".into()); }
+            }
+            ui.invoke_settings_changed();
+            let error = ui.get_settings_error();
+            if !error.is_empty() { return Err(error.to_string()); }
+        }
+        PaletteCommand::Profile(name) => {
+            ui.set_active_profile(name.clone().into());
+            ui.invoke_profile_changed(name.into());
+        }
+    }
+    Ok(())
+}
+
+const SESSION_LOG: &str = "sessions.csv";
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn ensure_session_log() -> std::io::Result<()> {
+    let path = data_path(SESSION_LOG);
+    if !path.exists() {
+        fs::write(path, "timestamp,mode,duration_minutes\n")?;
+    }
+    Ok(())
+}
+
+/// Mode column for stopwatch sessions in the log; they aren't pomodoros, so the
+/// per-period session counts leave them out.
+const STOPWATCH_LOG_KEY: &str = "stopwatch";
+
+const LOG_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Appends a finished session of `seconds` and returns it as a history row in `language`.
+fn append_session_log(language: &str, mode: &str, seconds: i32) -> HistoryEntry {
+    let ended = Local::now().naive_local();
+    let _ = ensure_session_log().and_then(|_| {
+        let mut file = fs::OpenOptions::new().append(true).open(data_path(SESSION_LOG))?;
+        let timestamp = ended.format(LOG_TIME_FORMAT).to_string();
+        writeln!(file, "{},{},{}", csv_field(&timestamp), csv_field(mode), log_minutes(seconds))
+    });
+    history_entry(language, ended, mode, seconds)
+}
+
+/// The duration column: whole minutes as before, a phase with seconds as a fraction
+/// (90 s is `1.5`, 5 s is `0.0833`), so short phases aren't rounded up in the totals.
+fn log_minutes(seconds: i32) -> String {
+    if seconds % 60 == 0 { return (seconds / 60).to_string(); }
+    let text = format!("{:.4}", seconds as f64 / 60.0);
+    text.trim_end_matches('0').to_string()
+}
+
+/// Reads a duration column back as seconds; four decimals round-trip exactly.
+fn parse_log_seconds(field: &str) -> Option<i32> {
+    let minutes: f64 = field.trim().parse().ok()?;
+    minutes.is_finite().then(|| (minutes * 60.0).round() as i32)
+}
+
+/// Longest the in-window history gets; older sessions stay in the CSV only.
+const HISTORY_LEN: usize = 50;
+
+/// The log records the mode key and when a session ended; the list shows the
+/// translated kind and when it started. Unknown keys are shown as written.
+fn history_entry(language: &str, ended: NaiveDateTime, mode: &str, seconds: i32) -> HistoryEntry {
+    let started = ended - chrono::Duration::seconds(seconds as i64);
+    let kind = match [Mode::Work, Mode::ShortBreak, Mode::LongBreak].into_iter().find(|m| mode_key(*m) == mode) {
+        Some(m) => mode_title(language, m),
+        None if mode == STOPWATCH_LOG_KEY => tr(language, "title.stopwatch"),
+        None => mode,
+    };
+    let duration = match (seconds / 60, seconds % 60) {
+        (0, secs) => format!("{secs} s"),
+        (minutes, 0) => format!("{minutes} min"),
+        (minutes, secs) => format!("{minutes} min {secs} s"),
+    };
+    HistoryEntry { started: started.format("%a %d %b %H:%M").to_string().into(), kind: kind.into(), duration: duration.into() }
+}
+
+/// The newest `HISTORY_LEN` sessions from the log, newest first.
+fn load_history(language: &str) -> Vec<HistoryEntry> {
+    let Ok(data) = fs::read_to_string(data_path(SESSION_LOG)) else { return Vec::new() };
+    // The header (and any malformed line) fails to parse and drops out.
+    data.lines()
+        .rev()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, ',');
+            let ended = NaiveDateTime::parse_from_str(fields.next()?, LOG_TIME_FORMAT).ok()?;
+            let mode = fields.next()?;
+            let seconds = parse_log_seconds(fields.next()?)?;
+            Some(history_entry(language, ended, mode, seconds))
+        })
+        .take(HISTORY_LEN)
+        .collect()
+}
+
+/// Past this size only the end of the log is read for the 7/30-day figures; 512 KiB is
+/// well over a month of sessions.
+const PERIOD_LOG_TAIL: u64 = 512 * 1024;
+
+/// Day and length in seconds of each logged work session that ended on or after `since`.
+fn work_sessions_since(since: NaiveDate) -> Vec<(NaiveDate, i32)> {
+    use std::io::{Seek, SeekFrom};
+    let Ok(mut file) = fs::File::open(data_path(SESSION_LOG)) else { return Vec::new() };
+    let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let skip = len.saturating_sub(PERIOD_LOG_TAIL);
+    let mut bytes = Vec::new();
+    if file.seek(SeekFrom::Start(skip)).and_then(|_| file.read_to_end(&mut bytes)).is_err() { return Vec::new() }
+    // A tail read starts mid-line; like the header, a partial line fails to parse.
+    String::from_utf8_lossy(&bytes).lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, ',');
+            let ended = NaiveDateTime::parse_from_str(fields.next()?, LOG_TIME_FORMAT).ok()?;
+            if fields.next()? != mode_key(Mode::Work) { return None; }
+            let seconds = parse_log_seconds(fields.next()?)?;
+            Some((ended.date(), seconds))
+        })
+        .filter(|(day, _)| *day >= since)
+        .collect()
+}
+
+/// Totals for the `days` days ending today; an empty log gives zeros.
+fn period_stats(label: &str, sessions: &[(NaiveDate, i32)], today: NaiveDate, days: i64) -> PeriodStats {
+    let since = today - chrono::Duration::days(days - 1);
+    let mut per_day = std::collections::BTreeMap::<NaiveDate, i32>::new();
+    let mut seconds = 0;
+    for (day, s) in sessions.iter().filter(|(day, _)| *day >= since) {
+        *per_day.entry(*day).or_default() += 1;
+        seconds += s;
+    }
+    let count: i32 = per_day.values().sum();
+    // Ties go to the most recent day.
+    let best = per_day.iter().max_by_key(|(_, n)| **n);
+    PeriodStats {
+        label: label.into(),
+        sessions: count,
+        per_day: format!("{:.1}", count as f32 / days as f32).into(),
+        focus: format_duration(seconds).into(),
+        best_day: best.map(|(day, n)| format!("{} ({n})", day.format("%a %d %b"))).unwrap_or_else(|| "—".into()).into(),
+    }
+}
+
+fn show_period_stats(ui: &AppWindow) {
+    let today = Local::now().date_naive();
+    let sessions = work_sessions_since(today - chrono::Duration::days(29));
+    let periods = vec![period_stats("7 days", &sessions, today, 7), period_stats("30 days", &sessions, today, 30)];
+    ui.set_period_stats(ModelRc::new(VecModel::from(periods)));
+}
+
+fn push_history(ui: &AppWindow, entry: HistoryEntry) {
+    let history = ui.get_history();
+    if let Some(model) = history.as_any().downcast_ref::<VecModel<HistoryEntry>>() {
+        model.insert(0, entry);
+        if model.row_count() > HISTORY_LEN { model.remove(HISTORY_LEN); }
+    }
+}
+
+fn stop_alarm(slot: &AlarmSlot) {
+    if let Some(sink) = slot.lock().unwrap().take() {
+        sink.stop();
+    }
+}
+
+fn fade_out_alarm(slot: &AlarmSlot) {
+    if let Some(sink) = slot.lock().unwrap().take() {
+        thread::spawn(move || {
+            let start = sink.volume();
+            for step in (0..10).rev() {
+                sink.set_volume(start * step as f32 / 10.0);
+                thread::sleep(Duration::from_millis(50));
+            }
+            sink.stop();
+        });
+    }
+}
+
+/// The banner setting from before Do Not Disturb turned it off. It's kept on disk,
+/// so a run that ended without lifting DND (a crash mid-phase) can restore it next time.
+const DND_SAVED_BANNERS: &str = "dnd_show_banners";
+
+/// Turns the desktop's notification banners off (`true`) or back to what they were
+/// before. Only GNOME's setting is known here; elsewhere this logs and does nothing.
+#[cfg(target_os = "linux")]
+fn set_dnd(enabled: bool) {
+    let saved = data_path(DND_SAVED_BANNERS);
+    let banners = if enabled {
+        if !saved.exists() {
+            let current = std::process::Command::new("gsettings")
+                .args(["get", "org.gnome.desktop.notifications", "show-banners"])
+                .output()
+                .ok()
+                .filter(|out| out.status.success())
+                .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string());
+            if let Some(current) = current { let _ = fs::write(&saved, current); }
+        }
+        "false".to_string()
+    } else {
+        // Nothing saved means the user's value wasn't read; banners on is the default.
+        let before = fs::read_to_string(&saved).map(|v| v.trim().to_string()).unwrap_or_default();
+        let _ = fs::remove_file(&saved);
+        if before == "false" { before } else { "true".to_string() }
+    };
+    let status = std::process::Command::new("gsettings")
+        .args(["set", "org.gnome.desktop.notifications", "show-banners", &banners])
+        .status();
+    match status {
+        Ok(s) if s.success() => {}
+        Ok(s) => eprintln!("warning: could not change Do Not Disturb (gsettings exited with {s})"),
+        Err(e) => eprintln!("warning: could not change Do Not Disturb ({e})"),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_dnd(_enabled: bool) {
+    eprintln!("warning: Do Not Disturb control isn't supported on this platform");
+}
+
+/// How long since the last keyboard or mouse input, from GNOME's idle monitor.
+/// `None` when that can't be asked, which turns idle auto-pause into a no-op.
+#[cfg(target_os = "linux")]
+fn idle_time() -> Option<Duration> {
+    let output = std::process::Command::new("gdbus")
+        .args([
+            "call", "--session",
+            "--dest", "org.gnome.Mutter.IdleMonitor",
+            "--object-path", "/org/gnome/Mutter/IdleMonitor/Core",
+            "--method", "org.gnome.Mutter.IdleMonitor.GetIdletime",
+        ])
+        .output()
+        .ok()?;
+    if !output.status.success() { return None; }
+    // Reply looks like "(uint64 12345,)".
+    let reply = String::from_utf8_lossy(&output.stdout);
+    let millis = reply.trim().trim_start_matches("(uint64 ").trim_end_matches(",)").parse().ok()?;
+    Some(Duration::from_millis(millis))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn idle_time() -> Option<Duration> {
+    None
+}
+
+/// Whether the session is locked, from GNOME's screensaver. `None` when that can't be
+/// asked, which turns pause-on-lock into a no-op.
+#[cfg(target_os = "linux")]
+fn screen_locked() -> Option<bool> {
+    let output = std::process::Command::new("gdbus")
+        .args([
+            "call", "--session",
+            "--dest", "org.gnome.ScreenSaver",
+            "--object-path", "/org/gnome/ScreenSaver",
+            "--method", "org.gnome.ScreenSaver.GetActive",
+        ])
+        .output()
+        .ok()?;
+    if !output.status.success() { return None; }
+    // Reply looks like "(true,)".
+    Some(String::from_utf8_lossy(&output.stdout).contains("true"))
+}
+
+/// The lock screen runs on its own desktop, which an app can't switch to.
+#[cfg(windows)]
+fn screen_locked() -> Option<bool> {
+    use windows::Win32::System::StationsAndDesktops::{CloseDesktop, OpenInputDesktop, SwitchDesktop, DESKTOP_CONTROL_FLAGS, DESKTOP_SWITCHDESKTOP};
+
+    unsafe {
+        let Ok(desktop) = OpenInputDesktop(DESKTOP_CONTROL_FLAGS(0), false, DESKTOP_SWITCHDESKTOP) else { return Some(true) };
+        let locked = SwitchDesktop(desktop).is_err();
+        let _ = CloseDesktop(desktop);
+        Some(locked)
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn screen_locked() -> Option<bool> {
+    None
+}
+
+/// How often the idle time is polled; it spawns a process, so not on every tick.
+const IDLE_POLL: Duration = Duration::from_secs(5);
+
+struct Tray {
+    icon: TrayIcon,
+    /// Disabled first menu row mirroring the tooltip.
+    status: MenuItem,
+    /// Last readout pushed to the tray, so unchanged ticks don't touch the tray API.
+    shown: RefCell<String>,
+    show: MenuItem,
+    toggle: MenuItem,
+    skip: MenuItem,
+    quit: MenuItem,
+}
+
+fn tray_icon_image() -> Option<Icon> {
+    let size = 32u32;
+    let center = size as f32 / 2.0 - 0.5;
+    let radius_sq = (size as f32 / 2.0 - 1.0).powi(2);
+    let mut rgba = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let dist_sq = (x as f32 - center).powi(2) + (y as f32 - center).powi(2);
+            rgba.extend_from_slice(if dist_sq <= radius_sq { &[243, 139, 168, 255] } else { &[0, 0, 0, 0] });
+        }
+    }
+    Icon::from_rgba(rgba, size, size).ok()
+}
+
+/// Returns `None` when the platform has no usable tray, leaving the app window-only.
+fn create_tray() -> Option<Tray> {
+    #[cfg(target_os = "linux")]
+    gtk::init().ok()?;
+    let status = MenuItem::new("Pomodoro", false, None);
+    let show = MenuItem::new("Show Window", true, None);
+    let toggle = MenuItem::new("Start", true, None);
+    let skip = MenuItem::new("Skip", true, None);
+    let quit = MenuItem::new("Quit", true, None);
+    let menu = Menu::new();
+    menu.append_items(&[&status, &show, &toggle, &skip, &quit]).ok()?;
+    let icon = TrayIconBuilder::new()
+        .with_menu(Box::new(menu))
+        .with_tooltip("Pomodoro")
+        .with_icon(tray_icon_image()?)
+        .build()
+        .ok()?;
+    Some(Tray { icon, status, shown: RefCell::default(), show, toggle, skip, quit })
+}
+
+/// Refreshes the "Focus 12:34 · 3 today" readout and the Start/Pause item.
+fn update_tray(tray: &OnceCell<Option<Tray>>, ui: &AppWindow, s: &AppState) {
+    if let Some(Some(tray)) = tray.get() {
+        let readout = format!("{} {} · {} today", mode_title(&s.config.language, s.mode), ui.get_timer_text(), s.stats.today_sessions);
+        if *tray.shown.borrow() != readout {
+            let _ = tray.icon.set_tooltip(Some(&readout));
+            tray.status.set_text(&readout);
+            *tray.shown.borrow_mut() = readout;
+        }
+        tray.toggle.set_text(if ui.get_is_running() { "Pause" } else { "Start" });
+    }
+}
+
+struct Hotkeys {
+    _manager: GlobalHotKeyManager,
+    toggle: Option<u32>,
+    reset: Option<u32>,
+}
+
+/// Registration problems (bad combo, combo taken by another app) are logged, not fatal.
+fn register_hotkeys(config: &AppConfig) -> Option<Hotkeys> {
+    let manager = match GlobalHotKeyManager::new() {
+        Ok(m) => m,
+        Err(e) => {
+            eprintln!("warning: global hotkeys unavailable ({e})");
+            return None;
+        }
+    };
+    let register = |combo: &str| -> Option<u32> {
+        if combo.trim().is_empty() { return None; }
+        let hotkey: HotKey = match combo.parse() {
+            Ok(h) => h,
+            Err(e) => {
+                eprintln!("warning: invalid hotkey {combo:?} ({e})");
+                return None;
+            }
+        };
+        match manager.register(hotkey) {
+            Ok(()) => Some(hotkey.id()),
+            Err(e) => {
+                eprintln!("warning: could not register hotkey {combo:?} ({e})");
+                None
+            }
+        }
+    };
+    let toggle = register(&config.hotkey_toggle);
+    let reset = register(&config.hotkey_reset);
+    Some(Hotkeys { _manager: manager, toggle, reset })
+}
+
+const MIN_MINUTES: i32 = 1;
+const MAX_MINUTES: i32 = 180;
+
+/// Shortest exact-seconds phase; anything quicker is over before the alarm is.
+const MIN_SECONDS: i32 = 5;
+
+/// An advanced seconds field: empty means "use the minutes", anything else must be a
+/// whole number of seconds and is clamped like the minute fields.
+fn parse_exact_seconds(input: &str, label: &str, current: Option<i32>, errors: &mut Vec<String>) -> Option<i32> {
+    let input = input.trim();
+    if input.is_empty() { return None; }
+    let max = MAX_MINUTES * 60;
+    match input.parse::<i32>() {
+        Ok(v) if (MIN_SECONDS..=max).contains(&v) => Some(v),
+        Ok(v) => {
+            errors.push(format!("{label} must be {MIN_SECONDS}-{max} s"));
+            Some(v.clamp(MIN_SECONDS, max))
+        }
+        Err(_) => {
+            errors.push(format!("{label} seconds must be a whole number"));
+            current
+        }
+    }
+}
+
+/// A plain number setting. Surrounding whitespace (a pasted newline, say) is fine;
+/// anything else that doesn't parse, "30min" included, is an error and keeps `current`.
+fn parse_setting<T: std::str::FromStr>(input: &str, label: &str, current: T, errors: &mut Vec<String>) -> T {
+    match input.trim().parse() {
+        Ok(v) => v,
+        Err(_) => {
+            errors.push(format!("{label} must be a whole number"));
+            current
+        }
+    }
+}
+
+enum Minutes { Valid(i32), Clamped(i32), Invalid }
+
+impl Minutes {
+    /// Trims first, like `parse_setting`, so "  30  " and "30\n" are plain 30.
+    fn parse(input: &str) -> Self {
+        match input.trim().parse::<i32>() {
+            Ok(v) if (MIN_MINUTES..=MAX_MINUTES).contains(&v) => Minutes::Valid(v),
+            Ok(v) => Minutes::Clamped(v.clamp(MIN_MINUTES, MAX_MINUTES)),
+            Err(_) => Minutes::Invalid,
+        }
+    }
+
+    fn resolve(&self, label: &str, current: i32, errors: &mut Vec<String>) -> i32 {
+        match *self {
+            Minutes::Valid(v) => v,
+            Minutes::Clamped(v) => {
+                errors.push(format!("{label} must be {MIN_MINUTES}-{MAX_MINUTES} min"));
+                v
+            }
+            Minutes::Invalid => {
+                errors.push(format!("{label} must be a whole number"));
+                current
+            }
+        }
+    }
+}
+
+/// Stable lowercase names for logs and webhook payloads.
+fn mode_key(mode: Mode) -> &'static str {
+    match mode {
+        Mode::Work => "work",
+        Mode::ShortBreak => "short_break",
+        Mode::LongBreak => "long_break",
+    }
+}
+
+/// Fire-and-forget POST on its own thread; failures are only logged.
+fn post_webhook(url: String, payload: serde_json::Value) {
+    thread::spawn(move || {
+        let result = ureq::post(&url)
+            .timeout(Duration::from_secs(10))
+            .set("Content-Type", "application/json")
+            .send_string(&payload.to_string());
+        if let Err(e) = result {
+            eprintln!("warning: webhook {url} failed ({e})");
+        }
+    });
+}
+
+#[derive(Serialize, Clone, Default)]
+struct StatusSnapshot {
+    mode: &'static str,
+    seconds_left: i32,
+    is_running: bool,
+    sessions_completed: i32,
+}
+
+/// Local-only HTTP endpoint answering every GET with the latest `StatusSnapshot`.
+/// The tick writes the snapshot; the server thread only ever reads it.
+struct StatusServer {
+    port: u16,
+    snapshot: Arc<Mutex<StatusSnapshot>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl StatusServer {
+    fn start(port: u16) -> Option<Self> {
+        let listener = match TcpListener::bind(("127.0.0.1", port)) {
+            Ok(listener) => listener,
+            Err(e) => {
+                eprintln!("warning: status server could not listen on 127.0.0.1:{port} ({e})");
+                return None;
+            }
+        };
+        // Non-blocking so the thread can notice `stop` between connections.
+        listener.set_nonblocking(true).ok()?;
+        let snapshot = Arc::new(Mutex::new(StatusSnapshot::default()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let (shared, stopping) = (snapshot.clone(), stop.clone());
+        let thread = thread::spawn(move || {
+            while !stopping.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) => serve_status(stream, &shared),
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => thread::sleep(Duration::from_millis(100)),
+                    Err(e) => eprintln!("warning: status server accept failed ({e})"),
+                }
+            }
+        });
+        Some(StatusServer { port, snapshot, stop, thread: Some(thread) })
+    }
+}
+
+impl Drop for StatusServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() { let _ = thread.join(); }
+    }
+}
+
+/// Read-only: `GET` returns the snapshot, anything else is refused. Control goes
+/// through the command socket, which only the current user can reach.
+fn serve_status(mut stream: TcpStream, snapshot: &Mutex<StatusSnapshot>) {
+    let _ = stream.set_nonblocking(false);
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(2)));
+    let mut request = [0u8; 1024];
+    let read = stream.read(&mut request).unwrap_or(0);
+    let response = if request[..read].starts_with(b"GET ") {
+        let body = serde_json::to_string(&*snapshot.lock().unwrap()).unwrap_or_default();
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nAccess-Control-Allow-Origin: *\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )
+    } else {
+        "HTTP/1.1 405 Method Not Allowed\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+    };
+    let _ = stream.write_all(response.as_bytes());
+}
+
+/// Local endpoint for scripts: each connection sends one command line, queued through
+/// the same channel as the tray and hotkeys. Only the current user can reach it: the
+/// unix socket is made owner-only, and a Windows pipe's default security only lets its
+/// creator (and administrators) write to it.
+struct CommandSocket {
+    path: PathBuf,
+    stop: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+fn command_socket_path(configured: &str) -> PathBuf {
+    let configured = configured.trim();
+    if !configured.is_empty() { return PathBuf::from(configured); }
+    if cfg!(windows) { PathBuf::from(r"\\.\pipe\pomodoro_timer") } else { data_path("command.sock") }
+}
+
+/// Reads one command and queues it, returning the reply for the client.
+fn read_socket_command(stream: &mut impl Read) -> &'static str {
+    let mut buffer = [0u8; 256];
+    let read = stream.read(&mut buffer).unwrap_or(0);
+    let command = match String::from_utf8_lossy(&buffer[..read]).trim().to_ascii_lowercase().as_str() {
+        "pause" => Command::Pause,
+        "resume" | "start" => Command::Start,
+        "toggle" => Command::Toggle,
+        "skip" => Command::Skip,
+        "reset" => Command::Reset,
+        _ => return "error: unknown command\n",
+    };
+    if send_command(command) { "ok\n" } else { "error: timer not ready\n" }
+}
+
+#[cfg(unix)]
+impl CommandSocket {
+    fn start(path: PathBuf) -> Option<Self> {
+        use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+        use std::os::unix::net::{UnixListener, UnixStream};
+        // A socket left behind by a crash refuses connections and can go; one that
+        // answers belongs to another instance, and anything else isn't ours to delete.
+        let stale = fs::symlink_metadata(&path).is_ok_and(|m| m.file_type().is_socket()) && UnixStream::connect(&path).is_err();
+        if stale { let _ = fs::remove_file(&path); }
+        let listener = match UnixListener::bind(&path) {
+            Ok(listener) => listener,
+            Err(e) => {
+                eprintln!("warning: command socket could not listen on {} ({e})", path.display());
+                return None;
+            }
+        };
+        // Its mode would otherwise come from the umask, letting other local users in.
+        if let Err(e) = fs::set_permissions(&path, fs::Permissions::from_mode(0o600)) {
+            eprintln!("warning: command socket not started; could not restrict {} to this user ({e})", path.display());
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+        listener.set_nonblocking(true).ok()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopping = stop.clone();
+        let thread = thread::spawn(move || {
+            while !stopping.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((mut stream, _)) => {
+                        let _ = stream.set_nonblocking(false);
+                        let _ = stream.set_read_timeout(Some(Duration::from_secs(2)));
+                        let reply = read_socket_command(&mut stream);
+                        let _ = stream.write_all(reply.as_bytes());
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => thread::sleep(Duration::from_millis(100)),
+                    Err(e) => eprintln!("warning: command socket accept failed ({e})"),
+                }
+            }
+        });
+        Some(CommandSocket { path, stop, thread: Some(thread) })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for CommandSocket {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() { let _ = thread.join(); }
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// One inbound instance of the pipe; `first` fails if another instance already owns the name.
+#[cfg(windows)]
+fn create_pipe(name: &[u16], first: bool) -> std::io::Result<fs::File> {
+    use std::os::windows::io::FromRawHandle;
+    use windows::core::PCWSTR;
+    use windows::Win32::Foundation::INVALID_HANDLE_VALUE;
+    use windows::Win32::Storage::FileSystem::{FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_INBOUND};
+    use windows::Win32::System::Pipes::{CreateNamedPipeW, PIPE_REJECT_REMOTE_CLIENTS, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT};
+    let mode = if first { PIPE_ACCESS_INBOUND | FILE_FLAG_FIRST_PIPE_INSTANCE } else { PIPE_ACCESS_INBOUND };
+    let pipe = unsafe { CreateNamedPipeW(PCWSTR(name.as_ptr()), mode, PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS, PIPE_UNLIMITED_INSTANCES, 0, 256, 0, None) };
+    if pipe == INVALID_HANDLE_VALUE { return Err(std::io::Error::last_os_error()); }
+    // The file owns the handle from here and closes it on drop.
+    Ok(unsafe { fs::File::from_raw_handle(pipe.0) })
+}
+
+#[cfg(windows)]
+impl CommandSocket {
+    fn start(path: PathBuf) -> Option<Self> {
+        use std::os::windows::ffi::OsStrExt;
+        use std::os::windows::io::AsRawHandle;
+        use windows::Win32::Foundation::{ERROR_PIPE_CONNECTED, HANDLE};
+        use windows::Win32::System::Pipes::ConnectNamedPipe;
+        let name: Vec<u16> = path.as_os_str().encode_wide().chain([0]).collect();
+        let mut pipe = match create_pipe(&name, true) {
+            Ok(pipe) => pipe,
+            Err(e) => {
+                eprintln!("warning: command pipe could not listen on {} ({e})", path.display());
+                return None;
+            }
+        };
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopping = stop.clone();
+        let thread = thread::spawn(move || loop {
+            // Blocks until a client opens the pipe; `drop` opens it once itself to end the wait.
+            let connected = match unsafe { ConnectNamedPipe(HANDLE(pipe.as_raw_handle()), None) } {
+                Ok(()) => true,
+                Err(e) => e.code() == ERROR_PIPE_CONNECTED.to_hresult(),
+            };
+            if stopping.load(Ordering::Relaxed) { return; }
+            if connected { read_socket_command(&mut pipe); }
+            pipe = match create_pipe(&name, false) {
+                Ok(next) => next,
+                Err(e) => {
+                    eprintln!("warning: command pipe stopped ({e})");
+                    return;
+                }
+            };
+        });
+        Some(CommandSocket { path, stop, thread: Some(thread) })
+    }
+}
+
+#[cfg(windows)]
+impl Drop for CommandSocket {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let _ = fs::OpenOptions::new().write(true).open(&self.path);
+        if let Some(thread) = self.thread.take() { let _ = thread.join(); }
+    }
+}
+
+fn message_or<'a>(custom: &'a str, fallback: &'a str) -> &'a str {
+    if custom.trim().is_empty() { fallback } else { custom }
+}
+
+const DEFAULT_APP_TITLE: &str = "Pomodoro";
+
+fn notify(title: &str, body: &str) {
+    let _ = Notification::new().summary(title).body(body).show();
+}
+
+/// A notification that stays up until it's dismissed.
+fn notify_persistent(title: &str, body: &str) {
+    let _ = Notification::new().summary(title).body(body).timeout(notify_rust::Timeout::Never).show();
+}
+
+/// A phase-end notification with "Start"/"Skip" buttons for the phase now waiting in
+/// `next`. Clicks arrive on a helper thread and are queued as commands.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn notify_with_actions(title: &str, body: &str, next: Mode) {
+    let (start, skip) = match next {
+        Mode::Work => ("Start focus", "Skip to break"),
+        _ => ("Start break", "Skip to work"),
+    };
+    let shown = Notification::new().summary(title).body(body).action("start", start).action("skip", skip).show();
+    let Ok(handle) = shown else { return };
+    thread::spawn(move || {
+        handle.wait_for_action(|action| match action {
+            "start" => { send_command(Command::Start); }
+            "skip" => { send_command(Command::Skip); }
+            _ => {}
+        });
+    });
+}
+
+/// Actions aren't supported by the notification backends here, so it's a plain one.
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn notify_with_actions(title: &str, body: &str, _next: Mode) {
+    notify(title, body);
+}
+
+/// Message key to text for one language. English lists every key; other tables may
+/// leave some out and those show in English.
+type Translation = &'static [(&'static str, &'static str)];
+
+const EN: Translation = &[
+    ("mode.work", "FOCUS PHASE"),
+    ("mode.short_break", "SHORT BREAK"),
+    ("mode.long_break", "LONG BREAK"),
+    ("mode.stopwatch", "STOPWATCH"),
+    ("title.work", "Focus"),
+    ("title.short_break", "Short Break"),
+    ("title.long_break", "Long Break"),
+    ("title.stopwatch", "Stopwatch"),
+    ("next", "Up next"),
+    ("notice.work_done", "Phase Complete!"),
+    ("notice.break_done", "Get to Work!"),
+    ("notice.cycle", "Cycle complete! Great work."),
+    ("notice.summary", "Today: {n} pomodoros, {t} focus"),
+    ("summary.goal_reached", ", goal reached"),
+    ("summary.goal_missed", ", goal not reached"),
+    ("notice.overrun", "You've focused for {t} without a break. Time to step away for a few minutes."),
+    ("notice.overrun_again", "Still no break after {t} of focus. Please take one now."),
+    ("notice.goal", "Daily goal reached: {n} pomodoros today! 🎉"),
+    ("notice.milestone", "Milestone: {n} pomodoros completed in total! 🏆"),
+];
+
+const DE: Translation = &[
+    ("mode.work", "FOKUSPHASE"),
+    ("mode.short_break", "KURZE PAUSE"),
+    ("mode.long_break", "LANGE PAUSE"),
+    ("mode.stopwatch", "STOPPUHR"),
+    ("title.work", "Fokus"),
+    ("title.short_break", "Kurze Pause"),
+    ("title.long_break", "Lange Pause"),
+    ("title.stopwatch", "Stoppuhr"),
+    ("next", "Als Nächstes"),
+    ("notice.work_done", "Phase abgeschlossen!"),
+    ("notice.break_done", "Zurück an die Arbeit!"),
+    ("notice.cycle", "Zyklus abgeschlossen! Gut gemacht."),
+    ("notice.summary", "Heute: {n} Pomodoros, {t} Fokus"),
+    ("summary.goal_reached", ", Ziel erreicht"),
+    ("summary.goal_missed", ", Ziel nicht erreicht"),
+    ("notice.overrun", "Du arbeitest seit {t} ohne Pause. Zeit, kurz aufzustehen."),
+    ("notice.overrun_again", "Immer noch keine Pause nach {t}. Bitte mach jetzt eine."),
+    ("notice.goal", "Tagesziel erreicht: heute {n} Pomodoros! 🎉"),
+    ("notice.milestone", "Meilenstein: insgesamt {n} Pomodoros abgeschlossen! 🏆"),
+];
+
+const ES: Translation = &[
+    ("mode.work", "FASE DE ENFOQUE"),
+    ("mode.short_break", "DESCANSO CORTO"),
+    ("mode.long_break", "DESCANSO LARGO"),
+    ("mode.stopwatch", "CRONÓMETRO"),
+    ("title.work", "Enfoque"),
+    ("title.short_break", "Descanso corto"),
+    ("title.long_break", "Descanso largo"),
+    ("title.stopwatch", "Cronómetro"),
+    ("next", "Siguiente"),
+    ("notice.work_done", "¡Fase completada!"),
+    ("notice.break_done", "¡A trabajar!"),
+    ("notice.cycle", "¡Ciclo completado! Buen trabajo."),
+    ("notice.summary", "Hoy: {n} pomodoros, {t} de concentración"),
+    ("summary.goal_reached", ", meta alcanzada"),
+    ("summary.goal_missed", ", meta no alcanzada"),
+    ("notice.overrun", "Llevas {t} de concentración sin descanso. Es hora de alejarte unos minutos."),
+    ("notice.overrun_again", "Sigues sin descansar tras {t}. Tómate un descanso ahora."),
+    ("notice.goal", "Meta diaria alcanzada: ¡{n} pomodoros hoy! 🎉"),
+    ("notice.milestone", "Hito: ¡{n} pomodoros completados en total! 🏆"),
+];
+
+const FR: Translation = &[
+    ("mode.work", "PHASE DE CONCENTRATION"),
+    ("mode.short_break", "PAUSE COURTE"),
+    ("mode.long_break", "PAUSE LONGUE"),
+    ("mode.stopwatch", "CHRONOMÈTRE"),
+    ("title.work", "Concentration"),
+    ("title.short_break", "Pause courte"),
+    ("title.long_break", "Pause longue"),
+    ("title.stopwatch", "Chronomètre"),
+    ("next", "Ensuite"),
+    ("notice.work_done", "Phase terminée !"),
+    ("notice.break_done", "Au travail !"),
+    ("notice.cycle", "Cycle terminé ! Beau travail."),
+    ("notice.summary", "Aujourd'hui : {n} pomodoros, {t} de concentration"),
+    ("summary.goal_reached", ", objectif atteint"),
+    ("summary.goal_missed", ", objectif non atteint"),
+    ("notice.overrun", "Vous êtes concentré depuis {t} sans pause. Il est temps de souffler quelques minutes."),
+    ("notice.overrun_again", "Toujours pas de pause après {t}. Faites-en une maintenant."),
+    ("notice.goal", "Objectif du jour atteint : {n} pomodoros aujourd'hui ! 🎉"),
+    ("notice.milestone", "Étape franchie : {n} pomodoros terminés au total ! 🏆"),
+];
+
+/// Code, name shown in settings, and table. A new language is one more row here.
+const LANGUAGES: &[(&str, &str, Translation)] = &[
+    ("en", "English", EN),
+    ("de", "Deutsch", DE),
+    ("es", "Español", ES),
+    ("fr", "Français", FR),
+];
+
+fn tr(language: &str, key: &'static str) -> &'static str {
+    let lookup = |table: Translation| table.iter().find(|(k, _)| *k == key).map(|(_, text)| *text);
+    LANGUAGES.iter()
+        .find(|(code, _, _)| *code == language)
+        .and_then(|(_, _, table)| lookup(table))
+        .or_else(|| lookup(EN))
+        .unwrap_or(key)
+}
+
+fn mode_label(language: &str, mode: Mode) -> &'static str {
+    match mode {
+        Mode::Work => tr(language, "mode.work"),
+        Mode::ShortBreak => tr(language, "mode.short_break"),
+        Mode::LongBreak => tr(language, "mode.long_break"),
+    }
+}
+
+/// Where the progress bar ends up with `urgency_gradient` on.
+const URGENT_COLOR: Color = Color::from_rgb_u8(255, 59, 48);
+
+/// Built-in work, short break and long break colors.
+const DEFAULT_COLORS: [&str; 3] = ["#f38ba8", "#9ece6a", "#7dcfff"];
+
+fn parse_hex_color(text: &str) -> Option<Color> {
+    let hex = text.trim().strip_prefix('#')?;
+    if hex.len() != 6 { return None; }
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    Some(Color::from_rgb_u8((value >> 16) as u8, (value >> 8) as u8, value as u8))
+}
+
+fn mode_color(config: &AppConfig, mode: Mode) -> Color {
+    let (configured, fallback) = match mode {
+        Mode::Work => (&config.work_color, DEFAULT_COLORS[0]),
+        Mode::ShortBreak => (&config.short_break_color, DEFAULT_COLORS[1]),
+        Mode::LongBreak => (&config.long_break_color, DEFAULT_COLORS[2]),
+    };
+    parse_hex_color(configured).or_else(|| parse_hex_color(fallback)).unwrap_or_default()
+}
+
+thread_local! {
+    /// Clock texts built so far, by second. Every phase counts through the same values,
+    /// so after its first run the timer reuses these (a refcount bump) rather than
+    /// allocating a new string each second.
+    static CLOCK_TEXTS: RefCell<Vec<Option<SharedString>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Values past the longest phase (a long stopwatch or pause) are formatted each time.
+const CLOCK_CACHE_LIMIT: i64 = MAX_MINUTES as i64 * 60;
+
+/// `MM:SS`.
+fn clock_text(seconds: i64) -> SharedString {
+    let format = || slint::format!("{:02}:{:02}", seconds / 60, seconds % 60);
+    if !(0..=CLOCK_CACHE_LIMIT).contains(&seconds) { return format(); }
+    CLOCK_TEXTS.with_borrow_mut(|texts| {
+        let i = seconds as usize;
+        if texts.len() <= i { texts.resize(i + 1, None); }
+        texts[i].get_or_insert_with(format).clone()
+    })
+}
+
+fn mode_title(language: &str, mode: Mode) -> &'static str {
+    match mode {
+        Mode::Work => tr(language, "title.work"),
+        Mode::ShortBreak => tr(language, "title.short_break"),
+        Mode::LongBreak => tr(language, "title.long_break"),
+    }
+}
+
+fn update_next_phase(s: &AppState, ui: &AppWindow) {
+    if s.config.timer_kind == TimerKind::Stopwatch {
+        ui.set_next_phase_text("".into());
+        return;
+    }
+    let next = s.next_mode();
+    let seconds = s.phase_seconds(next);
+    let language = &s.config.language;
+    ui.set_next_phase_text(slint::format!("{}: {} ({}:{:02})", tr(language, "next"), mode_title(language, next), seconds / 60, seconds % 60));
+}
+
+fn displayed_time(s: &AppState) -> SharedString {
+    if s.config.timer_kind == TimerKind::Stopwatch { return clock_text(s.stopwatch_elapsed().as_secs() as i64); }
+    let shown = if s.config.count_up { s.phase_total() - s.seconds_left } else { s.seconds_left };
+    clock_text(shown as i64)
+}
+
+fn update_timer_text(s: &AppState, ui: &AppWindow) {
+    ui.set_timer_text(displayed_time(s));
+}
+
+/// Drains the bar as the phase runs, or fills it with `invert_progress`. Phase
+/// changes pass `animated: false` so the bar jumps straight to the new phase's start
+/// instead of sweeping across it in the new mode's color.
+fn update_progress(s: &AppState, ui: &AppWindow, animated: bool) {
+    // While ticking, read the deadline so the bar moves smoothly between whole seconds.
+    // The stopwatch has no end, so its bar runs against one focus length for scale.
+    let left = if s.config.timer_kind == TimerKind::Stopwatch {
+        (s.phase_total() as f32 - s.stopwatch_elapsed().as_secs_f32()).max(0.0)
+    } else if animated { s.deadline.saturating_duration_since(Instant::now()).as_secs_f32() } else { s.seconds_left as f32 };
+    let remaining = left / s.phase_total().max(1) as f32;
+    ui.set_progress_animated(animated);
+    let progress = if s.config.invert_progress { 1.0 - remaining } else { remaining };
+    ui.set_progress(progress);
+    // How far the displayed minute has run, 0 at each MM:00 and rising to 1 just before the next.
+    ui.set_minute_progress((60.0 - left % 60.0) % 60.0 / 60.0);
+    let base = mode_color(&s.config, s.mode);
+    // Squared, so the shift stays subtle until the last stretch of the phase.
+    let urgency = if s.config.urgency_gradient { (1.0 - remaining.clamp(0.0, 1.0)).powi(2) } else { 0.0 };
+    ui.set_progress_color(base.mix(&URGENT_COLOR, 1.0 - urgency));
+    show_taskbar_progress(ui, animated.then_some(progress));
+}
+
+thread_local! {
+    /// Last taskbar value in thousandths, so the per-tick refresh only calls out on change.
+    static TASKBAR_PROGRESS: std::cell::Cell<Option<Option<u16>>> = const { std::cell::Cell::new(None) };
+}
+
+/// Mirrors the progress bar onto the taskbar button or dock icon; `None` clears it.
+fn show_taskbar_progress(ui: &AppWindow, progress: Option<f32>) {
+    let value = progress.map(|p| (p.clamp(0.0, 1.0) * 1000.0).round() as u16);
+    if TASKBAR_PROGRESS.with(|last| last.replace(Some(value))) == Some(value) { return; }
+    set_taskbar_progress(ui, value);
+}
+
+#[cfg(windows)]
+fn set_taskbar_progress(ui: &AppWindow, value: Option<u16>) {
+    use slint::winit_030::winit::raw_window_handle::{HasWindowHandle, RawWindowHandle};
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED};
+    use windows::Win32::UI::Shell::{ITaskbarList3, TaskbarList, TBPF_NOPROGRESS, TBPF_NORMAL};
+
+    thread_local! {
+        static TASKBAR: OnceCell<Option<ITaskbarList3>> = const { OnceCell::new() };
+    }
+    let hwnd = ui.window().with_winit_window(|window| match window.window_handle().map(|h| h.as_raw()) {
+        Ok(RawWindowHandle::Win32(handle)) => Some(HWND(handle.hwnd.get() as *mut _)),
+        _ => None,
+    });
+    let Some(Some(hwnd)) = hwnd else { return };
+    TASKBAR.with(|taskbar| {
+        let taskbar = taskbar.get_or_init(|| unsafe {
+            let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+            CoCreateInstance(&TaskbarList, None, CLSCTX_INPROC_SERVER).ok()
+        });
+        let Some(taskbar) = taskbar else { return };
+        let result = unsafe {
+            match value {
+                Some(v) => taskbar.SetProgressState(hwnd, TBPF_NORMAL).and_then(|_| taskbar.SetProgressValue(hwnd, v as u64, 1000)),
+                None => taskbar.SetProgressState(hwnd, TBPF_NOPROGRESS),
+            }
+        };
+        if let Err(e) = result { eprintln!("warning: could not update taskbar progress ({e})"); }
+    });
+}
+
+/// The dock has no progress bar of its own, so the badge shows the percentage.
+#[cfg(target_os = "macos")]
+fn set_taskbar_progress(_ui: &AppWindow, value: Option<u16>) {
+    use objc2::MainThreadMarker;
+    use objc2_app_kit::NSApplication;
+    use objc2_foundation::NSString;
+
+    let Some(mtm) = MainThreadMarker::new() else { return };
+    let label = value.map(|v| NSString::from_str(&format!("{}%", v / 10)));
+    NSApplication::sharedApplication(mtm).dockTile().setBadgeLabel(label.as_deref());
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn set_taskbar_progress(_ui: &AppWindow, _value: Option<u16>) {}
+
+fn apply_config_to_ui(config: &AppConfig, ui: &AppWindow) {
+    ui.set_work_setting(config.work_m.to_string().into());
+    ui.set_short_break_setting(config.short_m.to_string().into());
+    ui.set_long_break_setting(config.long_m.to_string().into());
+    let seconds_text = |s: Option<i32>| s.map(|s| s.to_string()).unwrap_or_default();
+    ui.set_work_seconds_setting(seconds_text(config.work_s).into());
+    ui.set_short_seconds_setting(seconds_text(config.short_s).into());
+    ui.set_long_seconds_setting(seconds_text(config.long_s).into());
+    ui.set_sessions_per_long_break_setting(config.sessions_per_long_break.to_string().into());
+    ui.set_schedule_by_focus(config.schedule_mode == ScheduleMode::FocusMinutes);
+    ui.set_long_break_after_setting(config.long_break_after_m.to_string().into());
+    ui.set_alarm_volume(config.alarm_volume);
+    ui.set_alarm_loop(config.alarm_loop);
+    ui.set_alarm_fade(config.alarm_fade);
+    ui.set_max_alarm_setting(config.max_alarm_seconds.to_string().into());
+    ui.set_warn_before_setting(config.warn_before_seconds.to_string().into());
+    ui.set_snooze_setting(config.snooze_m.to_string().into());
+    ui.set_auto_pause_on_idle(config.auto_pause_on_idle);
+    ui.set_pause_on_lock(config.pause_on_lock);
+    ui.set_confirm_quit(config.confirm_quit);
+    ui.set_autostart_on_launch(config.autostart_on_launch);
+    ui.set_focus_tick(config.focus_tick);
+    ui.set_language_index(LANGUAGES.iter().position(|(code, _, _)| *code == config.language).unwrap_or(0) as i32);
+    ui.set_daily_goal_setting(config.daily_goal.to_string().into());
+    ui.set_break_reminder_setting(config.break_reminder_m.to_string().into());
+    ui.set_day_summary(config.day_summary);
+    ui.set_day_summary_time(config.day_summary_time.clone().into());
+    ui.set_focus_tick_volume(config.focus_tick_volume);
+    ui.set_ambient_volume(config.ambient_volume);
+    show_ambient_name(config, ui);
+    ui.set_mini_mode(config.mini_mode);
+    ui.set_status_server(config.status_server);
+    ui.set_command_socket(config.command_socket);
+    ui.set_status_port_setting(config.status_port.to_string().into());
+    ui.set_idle_pause_setting(config.idle_pause_m.to_string().into());
+    ui.set_max_snoozes_setting(config.max_snoozes.to_string().into());
+    ui.set_visual_alarm(config.visual_alarm);
+    ui.set_count_up(config.count_up);
+    ui.set_stopwatch(config.timer_kind == TimerKind::Stopwatch);
+    ui.set_invert_progress(config.invert_progress);
+    ui.set_urgency_gradient(config.urgency_gradient);
+    ui.set_notifications_enabled(config.notifications_enabled);
+    ui.set_theme(config.theme.clone().into());
+    ui.set_work_color_setting(config.work_color.clone().into());
+    ui.set_short_color_setting(config.short_break_color.clone().into());
+    ui.set_long_color_setting(config.long_break_color.clone().into());
+    ui.set_stay_on_top(config.always_on_top);
+    ui.set_app_title(config.app_title.clone().into());
+    ui.set_work_done_message(config.work_done_message.clone().into());
+    ui.set_break_done_message(config.break_done_message.clone().into());
+    ui.set_webhook_url(config.webhook_url.clone().into());
+    ui.set_event_log(config.event_log);
+    ui.set_auto_start_breaks(config.auto_start_breaks);
+    ui.set_auto_start_work(config.auto_start_work);
+    ui.set_count_skipped_work(config.count_skipped_work);
+    ui.set_long_break_enabled(config.long_break_enabled);
+    ui.set_manage_dnd(config.manage_dnd);
+    ui.set_strict_mode(config.strict_mode);
+    show_alarm_name(config, ui);
+    ui.set_settings_error("".into());
+}
+
+fn show_alarm_name(config: &AppConfig, ui: &AppWindow) {
+    let target = AlarmTarget::from_index(ui.get_alarm_target());
+    let path = config.alarm_path(target);
+    let alarm_name = match target {
+        _ if !path.is_empty() => display_name(Path::new(path)),
+        AlarmTarget::Shared => "Default Bell".to_string(),
+        AlarmTarget::Cycle => "Same as focus end".to_string(),
+        _ => "Same as all phases".to_string(),
+    };
+    ui.set_alarm_name(alarm_name.into());
+}
+
+fn show_ambient_name(config: &AppConfig, ui: &AppWindow) {
+    let name = if config.ambient_path.is_empty() { "None".to_string() } else { display_name(Path::new(&config.ambient_path)) };
+    ui.set_ambient_name(name.into());
+}
+
+fn show_profiles(s: &AppState, ui: &AppWindow) {
+    let names: Vec<SharedString> = s.profiles.iter().map(|p| p.name.as_str().into()).collect();
+    ui.set_profile_names(ModelRc::new(VecModel::from(names)));
+    ui.set_active_profile(s.active_profile.as_str().into());
+}
+
+fn set_running(s: &mut AppState, ui: &AppWindow, running: bool) {
+    s.set_running(running);
+    ui.set_is_running(running);
+    if !running { show_taskbar_progress(ui, None); }
+}
+
+fn show_phase(s: &AppState, ui: &AppWindow) {
+    ui.set_mode_text(s.phase_label().into());
+    ui.set_stopwatch(s.config.timer_kind == TimerKind::Stopwatch);
+    ui.set_mode_color(mode_color(&s.config, s.mode));
+    ui.set_pause_locked(s.pause_locked());
+    ui.set_sessions_count(s.sessions_completed);
+    ui.set_today_sessions(s.stats.today_sessions);
+    ui.set_today_cycles(s.stats.today_cycles);
+    ui.set_today_focus_text(format_duration(s.stats.today_focus_seconds).into());
+    ui.set_focus_ratio_text(focus_ratio_text(&s.stats).into());
+    ui.set_streak_text(streak_text(&s.stats).into());
+    ui.set_daily_goal(s.config.daily_goal);
+    update_timer_text(s, ui);
+    update_progress(s, ui, false);
+    update_next_phase(s, ui);
+}
+
+/// "Stop & log": pauses the stopwatch, logs it and shows zero again.
+fn stop_stopwatch(s: &mut AppState, ui: &AppWindow) {
+    set_running(s, ui, false);
+    if let Some(entry) = s.stop_stopwatch() { push_history(ui, entry); }
+    s.paused_at = None;
+    s.accumulated_pause = Duration::ZERO;
+    ui.set_paused_text("00:00".into());
+    show_phase(s, ui);
+    s.save_session(false);
+}
+
+/// Applies a change of `timer_kind`: leaving the stopwatch logs what it ran; leaving
+/// the pomodoro pauses it where it stood, for when it's picked again.
+fn switch_timer_kind(s: &mut AppState, ui: &AppWindow, previous: TimerKind) {
+    if s.config.timer_kind == previous { return; }
+    if previous == TimerKind::Stopwatch {
+        stop_stopwatch(s, ui);
+    } else {
+        set_running(s, ui, false);
+        show_phase(s, ui);
+    }
+}
+
+#[must_use]
+fn advance_phase(s: &mut AppState, ui: &AppWindow, skipped: bool) -> Option<String> {
+    let transition = s.advance(skipped);
+    if let Some(entry) = transition.logged { push_history(ui, entry); }
+    show_phase(s, ui);
+    if transition.auto_start { s.log_event("start"); }
+    set_running(s, ui, transition.auto_start);
+    transition.notice
+}
+
+/// Smallest window that keeps the controls usable; matches `min-width`/`min-height` in app.slint.
+const MIN_WINDOW: (u32, u32) = (360, 480);
+
+/// How long a new window size must hold before it's saved, so a drag-resize writes once.
+const RESIZE_SETTLE: Duration = Duration::from_secs(1);
+
+/// The window's `preferred-width`/`preferred-height` in app.slint, and the mini view's size.
+const DEFAULT_WINDOW: (u32, u32) = (420, 720);
+const MINI_WINDOW: (u32, u32) = (240, 80);
+
+/// Sizes the window for the current view: fixed for the mini view, otherwise the saved size.
+fn apply_window_size(config: &AppConfig, ui: &AppWindow) {
+    let (width, height) = if config.mini_mode {
+        MINI_WINDOW
+    } else {
+        let (width, height) = config.window_width.zip(config.window_height).unwrap_or(DEFAULT_WINDOW);
+        (width.max(MIN_WINDOW.0), height.max(MIN_WINDOW.1))
+    };
+    ui.window().set_size(slint::LogicalSize::new(width as f32, height as f32));
+}
+
+fn window_size(ui: &AppWindow) -> (u32, u32) {
+    let size = ui.window().size().to_logical(ui.window().scale_factor());
+    (size.width.round() as u32, size.height.round() as u32)
+}
+
+fn window_position(ui: &AppWindow) -> (i32, i32) {
+    let position = ui.window().position();
+    (position.x, position.y)
+}
+
+/// Tracks a polled window value and reports it once it has held for `RESIZE_SETTLE`.
+struct Settle<T> {
+    pending: Option<(T, Instant)>,
+}
+
+impl<T: PartialEq> Settle<T> {
+    fn new() -> Self {
+        Settle { pending: None }
+    }
+
+    fn settled(&mut self, value: T, stored: &T) -> bool {
+        if value == *stored {
+            self.pending = None;
+            return false;
+        }
+        match &self.pending {
+            Some((p, since)) if *p == value && since.elapsed() >= RESIZE_SETTLE => {
+                self.pending = None;
+                true
+            }
+            Some((p, _)) if *p == value => false,
+            _ => {
+                self.pending = Some((value, Instant::now()));
+                false
+            }
+        }
+    }
+}
+
+/// Moves the window to its saved spot if a connected monitor still contains it, otherwise
+/// centers it on the primary monitor so an unplugged display can't strand it off-screen.
+/// Without a winit window there's nothing to check against, so the saved spot is skipped.
+fn restore_window_position(ui: &AppWindow, saved: (i32, i32)) {
+    let target = ui.window().with_winit_window(|window| {
+        let contains = |monitor: &slint::winit_030::winit::monitor::MonitorHandle| {
+            let (origin, size) = (monitor.position(), monitor.size());
+            (origin.x..origin.x + size.width as i32).contains(&saved.0)
+                && (origin.y..origin.y + size.height as i32).contains(&saved.1)
+        };
+        if window.available_monitors().any(|monitor| contains(&monitor)) {
+            return Some(saved);
+        }
+        let monitor = window.primary_monitor().or_else(|| window.available_monitors().next())?;
+        let (origin, area, size) = (monitor.position(), monitor.size(), window.outer_size());
+        Some((
+            origin.x + (area.width as i32 - size.width as i32) / 2,
+            origin.y + (area.height as i32 - size.height as i32) / 2,
+        ))
+    });
+    if let Some(Some((x, y))) = target {
+        ui.window().set_position(slint::PhysicalPosition::new(x, y));
+    }
+}
+
+/// Start/pause presses closer together than this are treated as one.
+const TOGGLE_DEBOUNCE: Duration = Duration::from_millis(200);
+
+const FLASH_DURATION: Duration = Duration::from_millis(1500);
+
+/// Pulses the colored overlay; it fades out on its own, so the next phase end can flash again.
+fn flash_window(ui: &AppWindow) {
+    ui.set_flashing(true);
+    let ui_handle = ui.as_weak();
+    Timer::single_shot(FLASH_DURATION, move || {
+        if let Some(ui) = ui_handle.upgrade() { ui.set_flashing(false); }
+    });
+}
+
+fn on_tick(s: &mut AppState, ui: &AppWindow, tray: &OnceCell<Option<Tray>>) -> Option<String> {
+    if s.stats.roll_day(Local::now().date_naive()) {
+        save_stats(&s.stats);
+        ui.set_today_sessions(0);
+        ui.set_today_cycles(0);
+        ui.set_today_focus_text(format_duration(0).into());
+        ui.set_focus_ratio_text(focus_ratio_text(&s.stats).into());
+        ui.set_streak_text(streak_text(&s.stats).into());
+    }
+    if s.undo_reset.as_ref().is_some_and(|undo| undo.taken.elapsed() >= UNDO_WINDOW) {
+        s.undo_reset = None;
+        ui.set_undo_available(false);
+    }
+    if s.alarm_status_at.is_some_and(|at| at.elapsed() >= ALARM_STATUS_TIME) {
+        s.alarm_status_at = None;
+        ui.set_alarm_status("".into());
+    }
+    let mut notice = None;
+    let mut changed = false;
+    if !ui.get_is_running() {
+        if s.paused_at.is_some() {
+            let paused = s.paused_total().as_secs();
+            if s.rendered_pause.replace(paused) != Some(paused) {
+                ui.set_paused_text(clock_text(paused as i64));
+                changed = true;
+            }
+        }
+    } else if s.config.timer_kind == TimerKind::Stopwatch {
+        let shown = s.stopwatch_elapsed().as_secs() as i32;
+        if s.rendered_second.replace(shown) != Some(shown) {
+            update_timer_text(s, ui);
+            changed = true;
+        }
+        update_progress(s, ui, true);
+    } else if s.tick() {
+        if let Some(problem) = s.play_alarm() {
+            eprintln!("warning: {problem}");
+            ui.set_alarm_status(problem.into());
+            s.alarm_status_at = Some(Instant::now());
+        }
+        ui.set_audio_available(s.audio_available());
+        if s.config.visual_alarm || !s.audio_available() { flash_window(ui); }
+        notice = advance_phase(s, ui, false);
+        changed = true;
+    } else {
+        // The tick is much shorter than a second: the bar moves every time,
+        // the text (and the tray tooltip) only when the shown second changes.
+        if s.rendered_second.replace(s.seconds_left) != Some(s.seconds_left) {
+            update_timer_text(s, ui);
+            changed = true;
+        }
+        update_progress(s, ui, true);
+        if s.warning_due() { s.play_warning(); }
+    }
+    if changed { update_tray(tray, ui, s); }
+    s.sync_tick(ui.get_is_running());
+    s.sync_ambient(ui.get_is_running());
+    s.publish_status(ui.get_is_running());
+    notice
+}
+
+/// Stops the timer and goes back to a fresh focus phase. A plain reset keeps the
+/// long-break cycle where it was; `full` also clears the session count and the
+/// focus time toward the next long break.
+fn reset_timer(s: &mut AppState, ui: &AppWindow, full: bool) {
+    s.log_event(if full { "full_reset" } else { "reset" });
+    s.undo_reset = Some(ResetUndo {
+        taken: Instant::now(),
+        running: ui.get_is_running(),
+        mode: s.mode,
+        sessions_completed: s.sessions_completed,
+        focus_since_long_break: s.focus_since_long_break,
+        seconds_left: s.seconds_left,
+        extra_seconds: s.extra_seconds,
+        resume_to: s.resume_to,
+        paused_total: s.paused_total(),
+        stopwatch: s.stopwatch_elapsed(),
+    });
+    ui.set_undo_available(true);
+    stop_alarm(&s.alarm);
+    s.sync_dnd(false);
+    clear_session();
+    s.mode = Mode::Work;
+    s.seconds_left = s.phase_seconds(Mode::Work);
+    s.paused_at = None;
+    s.accumulated_pause = Duration::ZERO;
+    s.extra_seconds = 0;
+    s.warned = false;
+    s.just_ended = None;
+    s.resume_to = None;
+    s.stopwatch_banked = Duration::ZERO;
+    s.stopwatch_since = None;
+    if full {
+        s.sessions_completed = 0;
+        s.focus_since_long_break = 0;
+    }
+    ui.set_is_running(false);
+    ui.set_paused_text("00:00".into());
+    show_phase(s, ui);
+}
+
+/// Puts back the phase a reset discarded. A running phase carries on as if the reset
+/// never happened, so the seconds that passed since still count down.
+fn undo_reset(s: &mut AppState, ui: &AppWindow) {
+    ui.set_undo_available(false);
+    let Some(undo) = s.undo_reset.take() else { return };
+    s.log_event("undo_reset");
+    s.mode = undo.mode;
+    s.sessions_completed = undo.sessions_completed;
+    s.focus_since_long_break = undo.focus_since_long_break;
+    s.extra_seconds = undo.extra_seconds;
+    s.resume_to = undo.resume_to;
+    let elapsed = if undo.running { undo.taken.elapsed().as_secs() as i32 } else { 0 };
+    s.seconds_left = (undo.seconds_left - elapsed).max(1);
+    s.stopwatch_banked = undo.stopwatch + Duration::from_secs(elapsed as u64);
+    show_phase(s, ui);
+    s.paused_at = None;
+    set_running(s, ui, undo.running);
+    // The pause clock picks up from where it stood.
+    s.accumulated_pause = undo.paused_total;
+    s.paused_at = (!undo.running).then(Instant::now);
+    ui.set_paused_text(clock_text(undo.paused_total.as_secs() as i64));
+}
+
+/// Terminal front-end for `--no-gui`: same state machine, config and alarm,
+/// driven by a sleep loop instead of the Slint timer.
+/// Blocks until Enter is pressed on the terminal.
+fn wait_for_enter(prompt: &str) {
+    print!("{prompt}");
+    let _ = std::io::stdout().flush();
+    let _ = std::io::stdin().read_line(&mut String::new());
+}
+
+/// `running` is false for a session resumed in the paused position, which waits for
+/// Enter before it counts down; a fresh one starts right away.
+fn run_headless(mut s: AppState, running: bool) {
+    println!("{} - press Ctrl+C to quit", mode_label(&s.config.language, s.mode));
+    if !s.audio_available() {
+        println!("No audio device found; phase changes will only show desktop notifications.");
+    }
+    // Ctrl+C is the only way out, so that's where Do Not Disturb gets lifted.
+    let manage_dnd = s.config.manage_dnd;
+    let _ = ctrlc::set_handler(move || {
+        if manage_dnd { set_dnd(false); }
+        std::process::exit(130);
+    });
+    if !running {
+        wait_for_enter(&format!("{} {} paused - press Enter to resume...", s.phase_label(), displayed_time(&s)));
+    }
+    s.set_running(true);
+    s.log_event("start");
+    // The stopwatch only ends by hand: Enter stops and logs it.
+    let stop = (s.config.timer_kind == TimerKind::Stopwatch).then(|| {
+        println!("Stopwatch - press Enter to stop and log");
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = std::io::stdin().read_line(&mut String::new());
+            let _ = tx.send(());
+        });
+        rx
+    });
+    let mut shown = String::new();
+    loop {
+        thread::sleep(TICK);
+        if let Some(stop) = &stop {
+            if stop.try_recv().is_ok() {
+                let logged = displayed_time(&s);
+                s.set_running(false);
+                s.stop_stopwatch();
+                s.sync_dnd(false);
+                clear_session();
+                println!("Logged {logged} of focus");
+                return;
+            }
+        } else if s.tick() {
+            if let Some(problem) = s.play_alarm() { eprintln!("\nwarning: {problem}"); }
+            let transition = s.advance(false);
+            // Like the window: a phase that doesn't auto-start waits paused, so Do Not
+            // Disturb, the tick and the ambient loop only follow once it runs.
+            s.set_running(transition.auto_start);
+            println!("\n{} (sessions: {})", mode_label(&s.config.language, s.mode), s.sessions_completed);
+            if let Some(body) = transition.notice { notify(&s.notification_title(), &body); }
+            if !transition.auto_start {
+                wait_for_enter("Press Enter to start...");
+                stop_alarm(&s.alarm);
+                s.set_running(true);
+            }
+            s.log_event("start");
+        } else if s.warning_due() {
+            s.play_warning();
+        }
+        if let Some(body) = s.break_reminder_due() { notify_persistent(&s.notification_title(), &body); }
+        if let Some(body) = s.day_summary_due(false) { notify(&s.notification_title(), &body); }
+        s.sync_tick(true);
+        s.sync_ambient(true);
+        s.publish_status(true);
+        let line = format!("{} {}", s.phase_label(), displayed_time(&s));
+        if line != shown {
+            print!("\r{line} ");
+            let _ = std::io::stdout().flush();
+            shown = line;
+        }
+    }
+}
+
+const USAGE: &str = "Usage: pomodoro_timer [OPTIONS]
+
+Options:
+  --work <MIN>    Focus length in minutes for this run
+  --short <MIN>   Short break length in minutes for this run
+  --long <MIN>    Long break length in minutes for this run
+  --start         Start the first focus phase right away
+  --no-gui        Run the timer in the terminal without a window
+  --log           Write the JSON-lines event log for this run
+  -h, --help      Print this help
+
+Overrides apply to this run only and are not saved unless changed in the settings.";
+
+#[derive(Default)]
+struct CliArgs {
+    work_m: Option<i32>,
+    short_m: Option<i32>,
+    long_m: Option<i32>,
+    no_gui: bool,
+    log: bool,
+    start: bool,
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<CliArgs, String> {
+    let mut cli = CliArgs::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let (label, slot) = match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                std::process::exit(0);
+            }
+            "--no-gui" => {
+                cli.no_gui = true;
+                continue;
+            }
+            "--log" => {
+                cli.log = true;
+                continue;
+            }
+            "--start" => {
+                cli.start = true;
+                continue;
+            }
+            "--work" => ("Work", &mut cli.work_m),
+            "--short" => ("Break", &mut cli.short_m),
+            "--long" => ("Long", &mut cli.long_m),
+            other => return Err(format!("unknown argument {other:?}")),
+        };
+        let value = args.next().ok_or_else(|| format!("{arg} needs a value"))?;
+        let mut errors = Vec::new();
+        match Minutes::parse(&value) {
+            Minutes::Invalid => return Err(format!("{arg}: {label} must be a whole number")),
+            minutes => *slot = Some(minutes.resolve(label, 0, &mut errors)),
+        }
+        for e in errors {
+            eprintln!("warning: {arg}: {e}, using {}", slot.unwrap_or_default());
+        }
+    }
+    Ok(cli)
 }
 
 fn main() -> Result<(), slint::PlatformError> {
+    let cli = parse_args(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("error: {e}\n\n{USAGE}");
+        std::process::exit(2);
+    });
+    let mut stats = load_stats();
+    if stats.roll_day(Local::now().date_naive()) {
+        save_stats(&stats);
+    }
+
+    // Still saved from a run that didn't get to lift Do Not Disturb.
+    if data_path(DND_SAVED_BANNERS).exists() { set_dnd(false); }
+
+    let mut app_state = AppState::new(load_config(), stats);
+    let saved_session = load_session();
+    let had_session = saved_session.is_some();
+    // A phase that already ran out needs no question; it's simply credited.
+    let resume = saved_session.filter(|saved| saved.ran_out() || confirm_resume(saved, !cli.no_gui));
+    // Only a resumed session brings its profile back, and before the overrides below,
+    // which apply on top of whichever profile is active.
+    if let Some(name) = resume.as_ref().and_then(|saved| saved.profile.as_deref()) {
+        app_state.restore_profile(name);
+    }
+    let config = &mut app_state.config;
+    let saved_durations = config.durations();
+    // A minutes override also wins over an exact-seconds length for that phase.
+    if let Some(m) = cli.work_m { (config.work_m, config.work_s) = (m, None); }
+    if let Some(m) = cli.short_m { (config.short_m, config.short_s) = (m, None); }
+    if let Some(m) = cli.long_m { (config.long_m, config.long_s) = (m, None); }
+    let has_overrides = cli.work_m.is_some() || cli.short_m.is_some() || cli.long_m.is_some();
+    app_state.saved_durations = has_overrides.then_some(saved_durations);
+    app_state.seconds_left = app_state.phase_seconds(Mode::Work);
+    app_state.log_forced = cli.log;
+    app_state.cache_alarms();
+    app_state.sync_status_server();
+    app_state.sync_command_socket();
+    let mut resume_running = false;
+    let mut resumed = false;
+    if let Some(saved) = resume {
+        resume_running = app_state.restore_session(&saved);
+        resumed = true;
+    }
+    if had_session { app_state.save_session(resume_running); }
+    // A resumed session keeps its own running state; autostart only decides a fresh one.
+    if !resumed && (cli.start || app_state.config.autostart_on_launch) {
+        resume_running = true;
+    }
+    if cli.no_gui {
+        run_headless(app_state, !resumed || resume_running);
+        return Ok(());
+    }
+
     let ui = AppWindow::new()?;
     let ui_handle = ui.as_weak();
-    let config = load_config();
+    if app_state.config.mini_mode || app_state.config.window_width.is_some() {
+        apply_window_size(&app_state.config, &ui);
+    }
 
-    ui.set_work_setting(config.work_m.to_string().into());
-    ui.set_short_break_setting(config.short_m.to_string().into());
-    ui.set_long_break_setting(config.long_m.to_string().into());
-    
-    let alarm_name = Path::new(&config.alarm_path)
-        .file_name()
-        .map(|n| n.to_string_lossy().into_owned())
-        .unwrap_or_else(|| "alarm.mp3".to_string());
-    ui.set_alarm_name(alarm_name.into());
-    ui.set_timer_text(format!("{:02}:00", config.work_m).into());
+    let language_names: Vec<SharedString> = LANGUAGES.iter().map(|(_, name, _)| (*name).into()).collect();
+    ui.set_language_names(ModelRc::new(VecModel::from(language_names)));
+    apply_config_to_ui(&app_state.config, &ui);
+    show_profiles(&app_state, &ui);
+    ui.set_today_sessions(app_state.stats.today_sessions);
+    ui.set_today_focus_text(format_duration(app_state.stats.today_focus_seconds).into());
 
-    let state = Rc::new(RefCell::new(AppState {
-        seconds_left: config.work_m * 60,
-        mode: Mode::Work,
-        sessions_completed: 0,
-        config,
-    }));
+    ui.set_history(ModelRc::new(VecModel::from(load_history(&app_state.config.language))));
+    show_phase(&app_state, &ui);
+    ui.set_audio_available(app_state.audio_available());
+    if resume_running {
+        set_running(&mut app_state, &ui, true);
+    }
+    let saved_position = app_state.config.window_x.zip(app_state.config.window_y);
+    let state = Rc::new(RefCell::new(app_state));
 
     let timer = Timer::default();
+    let tray_timer = Timer::default();
+    let window_timer = Timer::default();
+    let hotkey_timer = Timer::default();
+    let idle_timer = Timer::default();
+    let command_timer = Timer::default();
+    let hotkeys = register_hotkeys(&state.borrow().config);
+    let tray: Rc<OnceCell<Option<Tray>>> = Rc::new(OnceCell::new());
 
     let ui_copy = ui_handle.clone();
     let state_copy = state.clone();
     ui.on_settings_changed(move || {
         let ui = ui_copy.unwrap();
         let mut s = state_copy.borrow_mut();
-        s.config.work_m = ui.get_work_setting().parse().unwrap_or(s.config.work_m);
-        s.config.short_m = ui.get_short_break_setting().parse().unwrap_or(s.config.short_m);
-        s.config.long_m = ui.get_long_break_setting().parse().unwrap_or(s.config.long_m);
-        save_config(&s.config);
-        
-        if !ui.get_is_running() {
-            s.seconds_left = match s.mode {
-                Mode::Work => s.config.work_m * 60,
-                Mode::ShortBreak => s.config.short_m * 60,
-                Mode::LongBreak => s.config.long_m * 60,
-            };
-            ui.set_timer_text(format!("{:02}:00", s.seconds_left / 60).into());
+        let previous = s.config.durations();
+        let kind_before = s.config.timer_kind;
+        let phase_before = s.phase_seconds(s.mode);
+        let elapsed = s.phase_elapsed();
+        let mut errors = Vec::new();
+        let work = Minutes::parse(&ui.get_work_setting());
+        s.config.work_m = work.resolve("Work", s.config.work_m, &mut errors);
+        if let Minutes::Clamped(v) = work { ui.set_work_setting(v.to_string().into()); }
+        let short = Minutes::parse(&ui.get_short_break_setting());
+        s.config.short_m = short.resolve("Break", s.config.short_m, &mut errors);
+        if let Minutes::Clamped(v) = short { ui.set_short_break_setting(v.to_string().into()); }
+        let long = Minutes::parse(&ui.get_long_break_setting());
+        s.config.long_m = long.resolve("Long", s.config.long_m, &mut errors);
+        if let Minutes::Clamped(v) = long { ui.set_long_break_setting(v.to_string().into()); }
+        s.config.snooze_m = Minutes::parse(&ui.get_snooze_setting()).resolve("Snooze", s.config.snooze_m, &mut errors);
+        s.config.idle_pause_m = Minutes::parse(&ui.get_idle_pause_setting()).resolve("Idle", s.config.idle_pause_m, &mut errors);
+        s.config.work_s = parse_exact_seconds(&ui.get_work_seconds_setting(), "Work", s.config.work_s, &mut errors);
+        s.config.short_s = parse_exact_seconds(&ui.get_short_seconds_setting(), "Break", s.config.short_s, &mut errors);
+        s.config.long_s = parse_exact_seconds(&ui.get_long_seconds_setting(), "Long", s.config.long_s, &mut errors);
+        if s.config.durations() != previous {
+            s.saved_durations = None;
         }
+        s.config.sessions_per_long_break = parse_setting(&ui.get_sessions_per_long_break_setting(), "Sessions per long break", s.config.sessions_per_long_break, &mut errors);
+        s.config.schedule_mode = if ui.get_schedule_by_focus() { ScheduleMode::FocusMinutes } else { ScheduleMode::SessionCount };
+        s.config.long_break_after_m = parse_setting(&ui.get_long_break_after_setting(), "Long break after", s.config.long_break_after_m, &mut errors);
+        s.config.auto_start_breaks = ui.get_auto_start_breaks();
+        s.config.auto_start_work = ui.get_auto_start_work();
+        s.config.count_skipped_work = ui.get_count_skipped_work();
+        s.config.long_break_enabled = ui.get_long_break_enabled();
+        s.config.alarm_loop = ui.get_alarm_loop();
+        s.config.alarm_fade = ui.get_alarm_fade();
+        s.config.max_alarm_seconds = parse_setting(&ui.get_max_alarm_setting(), "Max alarm", s.config.max_alarm_seconds, &mut errors).max(0);
+        s.config.warn_before_seconds = parse_setting(&ui.get_warn_before_setting(), "Heads-up", s.config.warn_before_seconds, &mut errors).max(0);
+        // A new cap changes how much of each alarm is kept decoded; a no-op otherwise.
+        s.cache_alarms();
+        s.config.max_snoozes = parse_setting(&ui.get_max_snoozes_setting(), "Max snoozes", s.config.max_snoozes, &mut errors).max(0);
+        s.config.visual_alarm = ui.get_visual_alarm();
+        s.config.auto_pause_on_idle = ui.get_auto_pause_on_idle();
+        s.config.pause_on_lock = ui.get_pause_on_lock();
+        s.config.confirm_quit = ui.get_confirm_quit();
+        s.config.autostart_on_launch = ui.get_autostart_on_launch();
+        s.config.focus_tick = ui.get_focus_tick();
+        if let Some((code, _, _)) = usize::try_from(ui.get_language_index()).ok().and_then(|i| LANGUAGES.get(i)) {
+            if s.config.language != *code {
+                s.config.language = code.to_string();
+                ui.set_history(ModelRc::new(VecModel::from(load_history(code))));
+            }
+        }
+        s.config.timer_kind = if ui.get_stopwatch() { TimerKind::Stopwatch } else { TimerKind::Pomodoro };
+        switch_timer_kind(&mut s, &ui, kind_before);
+        ui.set_mode_text(s.phase_label().into());
+        s.config.daily_goal = parse_setting(&ui.get_daily_goal_setting(), "Daily goal", s.config.daily_goal, &mut errors).max(0);
+        ui.set_daily_goal(s.config.daily_goal);
+        s.config.break_reminder_m = parse_setting(&ui.get_break_reminder_setting(), "Break nudge", s.config.break_reminder_m, &mut errors).max(0);
+        s.config.day_summary = ui.get_day_summary();
+        s.config.day_summary_time = ui.get_day_summary_time().trim().to_string();
+        // Recomputed quietly: half-typed goals ("1" on the way to "10") mustn't celebrate.
+        s.goal_just_reached();
+        s.config.focus_tick_volume = ui.get_focus_tick_volume().clamp(0.0, 1.0);
+        if let Some(tick) = &s.tick { tick.set_volume(s.config.focus_tick_volume); }
+        s.config.ambient_volume = ui.get_ambient_volume().clamp(0.0, 1.0);
+        if let Some((_, Some(ambient))) = &s.ambient { ambient.set_volume(s.config.ambient_volume); }
+        s.config.status_server = ui.get_status_server();
+        s.config.command_socket = ui.get_command_socket();
+        s.config.status_port = parse_setting(&ui.get_status_port_setting(), "Status port", s.config.status_port, &mut errors);
+        ui.set_settings_error(errors.join("; ").into());
+        s.sync_status_server();
+        s.sync_command_socket();
+        s.config.work_color = ui.get_work_color_setting().trim().into();
+        s.config.short_break_color = ui.get_short_color_setting().trim().into();
+        s.config.long_break_color = ui.get_long_color_setting().trim().into();
+        ui.set_mode_color(mode_color(&s.config, s.mode));
+        s.config.count_up = ui.get_count_up();
+        s.config.invert_progress = ui.get_invert_progress();
+        s.config.urgency_gradient = ui.get_urgency_gradient();
+        s.config.notifications_enabled = ui.get_notifications_enabled();
+        s.config.always_on_top = ui.get_stay_on_top();
+        s.config.manage_dnd = ui.get_manage_dnd();
+        s.config.strict_mode = ui.get_strict_mode();
+        ui.set_pause_locked(s.pause_locked());
+        s.sync_dnd(ui.get_is_running());
+        s.config.app_title = ui.get_app_title().trim().to_string();
+        s.config.work_done_message = ui.get_work_done_message().into();
+        s.config.break_done_message = ui.get_break_done_message().into();
+        s.config.webhook_url = ui.get_webhook_url().trim().into();
+        s.config.event_log = ui.get_event_log();
+        s.persist_config();
+
+        if !ui.get_is_running() && s.refit_paused_phase(phase_before, elapsed) {
+            s.save_session(false);
+        }
+        update_timer_text(&s, &ui);
+        update_progress(&s, &ui, false);
+        update_next_phase(&s, &ui);
     });
 
     let ui_copy = ui_handle.clone();
     let state_copy = state.clone();
     ui.on_select_file(move || {
-        if let Some(path) = rfd::FileDialog::new().add_filter("Audio", &["mp3", "wav", "ogg"]).pick_file() {
+        if let Some(path) = rfd::FileDialog::new().add_filter("Audio", &["mp3", "wav", "ogg", "flac"]).pick_file() {
+            let ui = ui_copy.unwrap();
+            // Probe now so a file rodio can't play is rejected here rather than at alarm time.
+            let (format, length) = match probe_audio_file(&path) {
+                Ok(probed) => probed,
+                Err(message) => {
+                    ui.set_alarm_error(format!("{message}, keeping the current alarm").into());
+                    return;
+                }
+            };
             let mut s = state_copy.borrow_mut();
-            s.config.alarm_path = path.display().to_string();
-            save_config(&s.config);
-            let name = path.file_name().unwrap().to_string_lossy().into_owned();
-            ui_copy.unwrap().set_alarm_name(name.into());
+            let target = AlarmTarget::from_index(ui.get_alarm_target());
+            let path_text = path.to_string_lossy().into_owned();
+            // A new pick of the same path may be a changed file; decode it afresh.
+            s.alarm_cache.retain(|a| a.path != path_text);
+            *s.config.alarm_path_mut(target) = path_text;
+            s.cache_alarms();
+            s.persist_config();
+            show_audio_label(&ui, &path, format, length, AppWindow::get_alarm_name, AppWindow::set_alarm_name);
+            ui.set_alarm_error("".into());
         }
     });
 
-    ui.on_toggle_timer({
-        let ui_copy = ui_handle.clone();
-        move || { ui_copy.unwrap().set_is_running(!ui_copy.unwrap().get_is_running()); }
+    let ui_copy = ui_handle.clone();
+    let state_copy = state.clone();
+    ui.on_clear_alarm(move || {
+        let ui = ui_copy.unwrap();
+        let mut s = state_copy.borrow_mut();
+        s.config.alarm_path_mut(AlarmTarget::from_index(ui.get_alarm_target())).clear();
+        s.cache_alarms();
+        s.persist_config();
+        show_alarm_name(&s.config, &ui);
+        ui.set_alarm_error("".into());
+    });
+
+    let ui_copy = ui_handle.clone();
+    let state_copy = state.clone();
+    ui.on_select_ambient(move || {
+        if let Some(path) = rfd::FileDialog::new().add_filter("Audio", &["mp3", "wav", "ogg", "flac"]).pick_file() {
+            let ui = ui_copy.unwrap();
+            // Same check as the alarm, so an unplayable file never silently loops nothing.
+            let (format, length) = match probe_audio_file(&path) {
+                Ok(probed) => probed,
+                Err(message) => {
+                    ui.set_alarm_error(format!("{message}, keeping the current ambient sound").into());
+                    return;
+                }
+            };
+            let mut s = state_copy.borrow_mut();
+            s.config.ambient_path = path.to_string_lossy().into_owned();
+            s.persist_config();
+            // Picking again restarts the loop, and retries a file that didn't open before.
+            s.ambient = None;
+            s.sync_ambient(ui.get_is_running());
+            show_audio_label(&ui, &path, format, length, AppWindow::get_ambient_name, AppWindow::set_ambient_name);
+            ui.set_alarm_error("".into());
+        }
+    });
+
+    let ui_copy = ui_handle.clone();
+    let state_copy = state.clone();
+    ui.on_clear_ambient(move || {
+        let ui = ui_copy.unwrap();
+        let mut s = state_copy.borrow_mut();
+        s.config.ambient_path.clear();
+        s.persist_config();
+        s.sync_ambient(ui.get_is_running());
+        show_ambient_name(&s.config, &ui);
+    });
+
+    let ui_copy = ui_handle.clone();
+    let state_copy = state.clone();
+    ui.on_alarm_target_changed(move || {
+        let ui = ui_copy.unwrap();
+        show_alarm_name(&state_copy.borrow().config, &ui);
+        ui.set_alarm_error("".into());
+    });
+
+    let state_copy = state.clone();
+    ui.on_volume_changed(move |volume| {
+        let volume = volume.clamp(0.0, 1.0);
+        let mut s = state_copy.borrow_mut();
+        s.config.alarm_volume = volume;
+        s.persist_config();
+        let active = s.alarm.lock().unwrap();
+        if let Some(sink) = active.as_ref() {
+            sink.set_volume(volume);
+        }
+    });
+
+    let ui_copy = ui_handle.clone();
+    let state_copy = state.clone();
+    ui.on_test_alarm(move || {
+        let ui = ui_copy.unwrap();
+        let mut s = state_copy.borrow_mut();
+        let target = AlarmTarget::from_index(ui.get_alarm_target());
+        if target == AlarmTarget::Warning {
+            s.play_warning();
+            return;
+        }
+        let path = s.config.effective_alarm(target).to_string();
+        let problem = s.play_alarm_file(&path);
+        ui.set_audio_available(s.audio_available());
+        ui.set_alarm_error(problem.unwrap_or_default().into());
+    });
+
+    let ui_copy = ui_handle.clone();
+    let state_copy = state.clone();
+    ui.on_profile_changed(move |name| {
+        let ui = ui_copy.unwrap();
+        let mut s = state_copy.borrow_mut();
+        let kind_before = s.config.timer_kind;
+        if s.switch_profile(&name, ui.get_is_running()) {
+            switch_timer_kind(&mut s, &ui, kind_before);
+            s.cache_alarms();
+            s.sync_dnd(ui.get_is_running());
+            s.persist_config();
+            apply_config_to_ui(&s.config, &ui);
+            update_timer_text(&s, &ui);
+            update_next_phase(&s, &ui);
+            if !ui.get_is_running() { update_progress(&s, &ui, false); }
+        }
+        show_profiles(&s, &ui);
+    });
+
+    let ui_copy = ui_handle.clone();
+    let state_copy = state.clone();
+    ui.on_add_profile(move |name| {
+        let ui = ui_copy.unwrap();
+        let mut s = state_copy.borrow_mut();
+        let name = name.trim();
+        if name.is_empty() || s.profiles.iter().any(|p| p.name == name) { return; }
+        let config = s.config_to_save();
+        s.profiles.push(Profile { name: name.to_string(), config });
+        s.switch_profile(name, ui.get_is_running());
+        s.persist_config();
+        show_profiles(&s, &ui);
+    });
+
+    let state_copy = state.clone();
+    ui.on_theme_changed(move |theme| {
+        let mut s = state_copy.borrow_mut();
+        s.config.theme = theme.into();
+        s.persist_config();
+    });
+
+    let ui_copy = ui_handle.clone();
+    ui.on_period_stats_requested(move || {
+        show_period_stats(&ui_copy.unwrap());
+    });
+
+    ui.on_export_stats(|| {
+        if let Some(path) = rfd::FileDialog::new().add_filter("CSV", &["csv"]).set_file_name(SESSION_LOG).save_file() {
+            let _ = ensure_session_log().and_then(|_| fs::copy(data_path(SESSION_LOG), path));
+        }
+    });
+
+    let state_copy = state.clone();
+    ui.on_export_config(move || {
+        let Some(path) = rfd::FileDialog::new().add_filter("JSON", &["json"]).set_file_name("pomodoro-settings.json").save_file() else { return };
+        let file = state_copy.borrow().config_file();
+        let result = serde_json::to_string_pretty(&file).map_err(std::io::Error::other).and_then(|json| fs::write(&path, json));
+        if let Err(e) = result { eprintln!("warning: could not export settings to {} ({e})", path.display()); }
+    });
+
+    let ui_copy = ui_handle.clone();
+    let state_copy = state.clone();
+    ui.on_import_config(move || {
+        let Some(path) = rfd::FileDialog::new().add_filter("JSON", &["json"]).pick_file() else { return };
+        let ui = ui_copy.unwrap();
+        let file = match ConfigFile::import(&path) {
+            Ok(file) => file,
+            Err(message) => {
+                ui.set_settings_error(format!("Import failed: {message}").into());
+                return;
+            }
+        };
+        let mut s = state_copy.borrow_mut();
+        s.replace_config(file, ui.get_is_running());
+        apply_config_to_ui(&s.config, &ui);
+        apply_window_size(&s.config, &ui);
+        show_profiles(&s, &ui);
+        show_phase(&s, &ui);
+        ui.set_settings_error("".into());
+    });
+
+    let state_copy = state.clone();
+    ui.on_stop_alarm(move || {
+        let s = state_copy.borrow();
+        if s.config.alarm_fade { fade_out_alarm(&s.alarm) } else { stop_alarm(&s.alarm) }
+    });
+
+    let ui_copy = ui_handle.clone();
+    let state_copy = state.clone();
+    ui.on_toggle_timer(move || {
+        let ui = ui_copy.unwrap();
+        let mut s = state_copy.borrow_mut();
+        if s.last_toggle.is_some_and(|t| t.elapsed() < TOGGLE_DEBOUNCE) { return; }
+        s.last_toggle = Some(Instant::now());
+        let running = !ui.get_is_running();
+        if !running && s.pause_locked() {
+            eprintln!("Strict mode: pausing focus is disabled (reset still works)");
+            s.log_event("pause_blocked");
+            return;
+        }
+        let fresh = s.seconds_left == s.phase_total();
+        if running { s.just_ended = None; }
+        s.log_event(match (running, fresh) { (false, _) => "pause", (true, true) => "start", (true, false) => "resume" });
+        set_running(&mut s, &ui, running);
+    });
+
+    let ui_copy = ui_handle.clone();
+    let state_copy = state.clone();
+    ui.on_mini_mode_changed(move |mini| {
+        let ui = ui_copy.unwrap();
+        let mut s = state_copy.borrow_mut();
+        s.config.mini_mode = mini;
+        s.persist_config();
+        apply_window_size(&s.config, &ui);
+    });
+
+    let ui_copy = ui_handle.clone();
+    ui.on_drag_window(move || {
+        let Some(ui) = ui_copy.upgrade() else { return };
+        ui.window().with_winit_window(|window| { let _ = window.drag_window(); });
     });
 
     let ui_copy = ui_handle.clone();
     let state_copy = state.clone();
     ui.on_reset_timer(move || {
+        reset_timer(&mut state_copy.borrow_mut(), &ui_copy.unwrap(), false);
+    });
+
+    let ui_copy = ui_handle.clone();
+    let state_copy = state.clone();
+    ui.on_stop_stopwatch(move || {
+        stop_stopwatch(&mut state_copy.borrow_mut(), &ui_copy.unwrap());
+    });
+
+    let ui_copy = ui_handle.clone();
+    let state_copy = state.clone();
+    ui.on_undo_reset(move || {
+        undo_reset(&mut state_copy.borrow_mut(), &ui_copy.unwrap());
+    });
+
+    let ui_copy = ui_handle.clone();
+    let state_copy = state.clone();
+    ui.on_full_reset(move || {
+        reset_timer(&mut state_copy.borrow_mut(), &ui_copy.unwrap(), true);
+    });
+
+    let state_copy = state.clone();
+    ui.on_command_hint(move |input| {
+        let names: Vec<String> = state_copy.borrow().profiles.iter().map(|p| p.name.clone()).collect();
+        palette_hint(&input, &names).into()
+    });
+
+    let ui_copy = ui_handle.clone();
+    let state_copy = state.clone();
+    ui.on_run_command(move |input| {
+        let ui = ui_copy.unwrap();
+        let names: Vec<String> = state_copy.borrow().profiles.iter().map(|p| p.name.clone()).collect();
+        match parse_palette(&input, &names).and_then(|command| run_palette_command(&ui, command)) {
+            Ok(()) => {
+                ui.set_palette_open(false);
+                ui.set_palette_hint("".into());
+            }
+            Err(message) => ui.set_palette_hint(message.into()),
+        }
+    });
+
+    let ui_copy = ui_handle.clone();
+    let state_copy = state.clone();
+    ui.on_extend(move |minutes| {
+        let ui = ui_copy.unwrap();
+        let mut s = state_copy.borrow_mut();
+        if s.config.timer_kind == TimerKind::Stopwatch { return; }
+        stop_alarm(&s.alarm);
+        s.log_event("extend");
+        if s.extend(minutes) {
+            show_phase(&s, &ui);
+            set_running(&mut s, &ui, true);
+        } else {
+            update_timer_text(&s, &ui);
+            update_progress(&s, &ui, false);
+            s.save_session(ui.get_is_running());
+        }
+    });
+
+    let ui_copy = ui_handle.clone();
+    let state_copy = state.clone();
+    ui.on_snooze(move || {
         let ui = ui_copy.unwrap();
         let mut s = state_copy.borrow_mut();
-        s.mode = Mode::Work;
-        s.seconds_left = s.config.work_m * 60;
-        ui.set_is_running(false);
-        ui.set_timer_text(format!("{:02}:00", s.config.work_m).into());
-        ui.set_mode_text("FOCUS PHASE".into());
-        ui.set_mode_color(Color::from_rgb_u8(243, 139, 168));
-        ui.set_progress(1.0);
+        if s.snooze() {
+            stop_alarm(&s.alarm);
+            s.log_event("snooze");
+            show_phase(&s, &ui);
+            set_running(&mut s, &ui, true);
+        }
+    });
+
+    let ui_copy = ui_handle.clone();
+    let state_copy = state.clone();
+    ui.on_skip_phase(move || {
+        let ui = ui_copy.unwrap();
+        if state_copy.borrow().config.timer_kind == TimerKind::Stopwatch {
+            stop_stopwatch(&mut state_copy.borrow_mut(), &ui);
+            return;
+        }
+        let notice = advance_phase(&mut state_copy.borrow_mut(), &ui, true);
+        let title = state_copy.borrow().notification_title();
+        if let Some(body) = notice { notify(&title, &body); }
     });
 
     let ui_copy = ui_handle.clone();
     let state_copy = state.clone();
-    timer.start(TimerMode::Repeated, std::time::Duration::from_secs(1), move || {
+    let tray_copy = tray.clone();
+    timer.start(TimerMode::Repeated, TICK, move || {
         let ui = match ui_copy.upgrade() { Some(ui) => ui, None => return };
-        if !ui.get_is_running() { return; }
+        if let Some(warning) = take_save_warning() { ui.set_save_warning(warning.into()); }
+        let notice = on_tick(&mut state_copy.borrow_mut(), &ui, &tray_copy);
+        let title = state_copy.borrow().notification_title();
+        let reminder = state_copy.borrow_mut().break_reminder_due();
+        if let Some(body) = reminder { notify_persistent(&title, &body); }
+        let summary = state_copy.borrow_mut().day_summary_due(false);
+        if let Some(body) = summary { notify(&title, &body); }
+        // Shown only after the borrow is released: `show()` can block on the notification
+        // daemon, and nothing may re-enter state while a `borrow_mut()` is live.
+        let Some(body) = notice else { return };
+        if ui.get_is_running() {
+            notify(&title, &body);
+        } else {
+            notify_with_actions(&title, &body, state_copy.borrow().mode);
+        }
+    });
+
+    // The tray is created lazily from inside the running event loop, which macOS requires,
+    // and on Linux its GTK loop is pumped here since Slint doesn't drive GTK.
+    let ui_copy = ui_handle.clone();
+    let state_copy = state.clone();
+    let tray_copy = tray.clone();
+    tray_timer.start(TimerMode::Repeated, Duration::from_millis(100), move || {
+        let Some(tray) = tray_copy.get_or_init(create_tray) else { return };
+        #[cfg(target_os = "linux")]
+        while gtk::events_pending() { gtk::main_iteration_do(false); }
+        let ui = match ui_copy.upgrade() { Some(ui) => ui, None => return };
+        while let Ok(event) = MenuEvent::receiver().try_recv() {
+            if event.id() == tray.show.id() {
+                let _ = ui.show();
+            } else if event.id() == tray.toggle.id() {
+                ui.invoke_toggle_timer();
+            } else if event.id() == tray.skip.id() {
+                ui.invoke_skip_phase();
+            } else if event.id() == tray.quit.id() {
+                let (enabled, mode) = { let s = state_copy.borrow(); (s.config.confirm_quit, s.mode) };
+                if confirm_quit(enabled, mode, ui.get_is_running()) { let _ = slint::quit_event_loop(); }
+            }
+            update_tray(&tray_copy, &ui, &state_copy.borrow());
+        }
+    });
 
+    // Slint has no resize or move callback, so size and position are polled and saved once they settle.
+    let ui_copy = ui_handle.clone();
+    let state_copy = state.clone();
+    let mut known = None;
+    let mut size_settle = Settle::new();
+    let mut position_settle = Settle::new();
+    window_timer.start(TimerMode::Repeated, Duration::from_millis(500), move || {
+        let Some(ui) = ui_copy.upgrade() else { return };
+        if !ui.window().is_visible() { return; }
         let mut s = state_copy.borrow_mut();
-        if s.seconds_left > 0 {
-            s.seconds_left -= 1;
-            ui.set_timer_text(format!("{:02}:{:02}", s.seconds_left / 60, s.seconds_left % 60).into());
-            let total = match s.mode {
-                Mode::Work => (s.config.work_m * 60) as f32,
-                Mode::ShortBreak => (s.config.short_m * 60) as f32,
-                Mode::LongBreak => (s.config.long_m * 60) as f32,
-            };
-            ui.set_progress(s.seconds_left as f32 / total);
-        } else {
-            play_alarm(s.config.alarm_path.clone());
-            match s.mode {
-                Mode::Work => {
-                    s.sessions_completed += 1;
-                    ui.set_sessions_count(s.sessions_completed);
-                    if s.sessions_completed % 4 == 0 {
-                        s.mode = Mode::LongBreak; s.seconds_left = s.config.long_m * 60;
-                        ui.set_mode_text("LONG BREAK".into()); ui.set_mode_color(Color::from_rgb_u8(125, 207, 255));
-                    } else {
-                        s.mode = Mode::ShortBreak; s.seconds_left = s.config.short_m * 60;
-                        ui.set_mode_text("SHORT BREAK".into()); ui.set_mode_color(Color::from_rgb_u8(158, 206, 106));
-                    }
-                    let _ = Notification::new().summary("Pomodoro").body("Phase Complete!").show();
-                }
-                _ => {
-                    s.mode = Mode::Work; s.seconds_left = s.config.work_m * 60;
-                    ui.set_mode_text("FOCUS PHASE".into()); ui.set_mode_color(Color::from_rgb_u8(243, 139, 168));
-                    let _ = Notification::new().summary("Pomodoro").body("Get to Work!").show();
-                }
+        let size = window_size(&ui);
+        if !s.config.mini_mode && size.0 >= MIN_WINDOW.0 && size.1 >= MIN_WINDOW.1 {
+            // First run: the .slint default is the baseline, not something to save.
+            let stored = s.config.window_width.zip(s.config.window_height);
+            let known = *known.get_or_insert(stored.unwrap_or(size));
+            if size_settle.settled(size, &stored.unwrap_or(known)) {
+                s.config.window_width = Some(size.0);
+                s.config.window_height = Some(size.1);
+                s.persist_config();
+            }
+        }
+        let position = window_position(&ui);
+        let stored = s.config.window_x.zip(s.config.window_y).unwrap_or(position);
+        if position_settle.settled(position, &stored) {
+            s.config.window_x = Some(position.0);
+            s.config.window_y = Some(position.1);
+            s.persist_config();
+        }
+    });
+
+    let (commands, queued) = mpsc::channel();
+    let _ = command_channel().set(commands);
+    let ui_copy = ui_handle.clone();
+    command_timer.start(TimerMode::Repeated, Duration::from_millis(100), move || {
+        let Some(ui) = ui_copy.upgrade() else { return };
+        while let Ok(command) = queued.try_recv() { apply_command(&ui, command); }
+    });
+
+    // Pauses focus after `idle_pause_m` without input or while the screen is locked, and
+    // picks it back up once the user is back. Both checks share one flag, so focus only
+    // resumes when neither of them still says "away".
+    let ui_copy = ui_handle.clone();
+    let state_copy = state.clone();
+    let (mut warned_idle, mut warned_lock) = (false, false);
+    idle_timer.start(TimerMode::Repeated, IDLE_POLL, move || {
+        let Some(ui) = ui_copy.upgrade() else { return };
+        let mut s = state_copy.borrow_mut();
+        if !(s.config.auto_pause_on_idle || s.config.pause_on_lock) || s.mode != Mode::Work { return; }
+        let running = ui.get_is_running();
+        if !running && !s.auto_paused { return; }
+        let idle = s.config.auto_pause_on_idle.then(idle_time).and_then(|idle| {
+            if idle.is_none() && !warned_idle { eprintln!("warning: idle time isn't available here, so the timer won't auto-pause"); }
+            warned_idle |= idle.is_none();
+            idle.map(|idle| idle >= Duration::from_secs(s.config.idle_pause_m.max(1) as u64 * 60))
+        });
+        let locked = s.config.pause_on_lock.then(screen_locked).and_then(|locked| {
+            if locked.is_none() && !warned_lock { eprintln!("warning: screen lock state isn't available here, so the timer won't pause on lock"); }
+            warned_lock |= locked.is_none();
+            locked
+        });
+        if idle.is_none() && locked.is_none() { return; }
+        let (idle, locked) = (idle.unwrap_or(false), locked.unwrap_or(false));
+        let away = idle || locked;
+        if running && away {
+            s.log_event(if locked { "lock_pause" } else { "idle_pause" });
+            set_running(&mut s, &ui, false);
+            s.auto_paused = true;
+        } else if !running && !away {
+            s.log_event("resume");
+            set_running(&mut s, &ui, true);
+        }
+    });
+
+    let ui_copy = ui_handle.clone();
+    hotkey_timer.start(TimerMode::Repeated, Duration::from_millis(100), move || {
+        let (Some(hotkeys), Some(ui)) = (&hotkeys, ui_copy.upgrade()) else { return };
+        while let Ok(event) = GlobalHotKeyEvent::receiver().try_recv() {
+            if event.state != HotKeyState::Pressed { continue; }
+            if Some(event.id) == hotkeys.toggle {
+                ui.invoke_toggle_timer();
+            } else if Some(event.id) == hotkeys.reset {
+                ui.invoke_reset_timer();
             }
         }
     });
 
-    ui.run()
-}
\ No newline at end of file
+    // With a tray, closing the window only hides it so a running pomodoro keeps ticking;
+    // the tray's Quit item is the real exit, and that's where quitting is confirmed.
+    let ui_copy = ui_handle.clone();
+    let state_copy = state.clone();
+    let tray_copy = tray.clone();
+    ui.window().on_close_requested(move || {
+        if !matches!(tray_copy.get(), Some(Some(_))) {
+            let Some(ui) = ui_copy.upgrade() else { return CloseRequestResponse::HideWindow };
+            let (enabled, mode) = { let s = state_copy.borrow(); (s.config.confirm_quit, s.mode) };
+            if !confirm_quit(enabled, mode, ui.get_is_running()) { return CloseRequestResponse::KeepWindowShown; }
+            let _ = slint::quit_event_loop();
+        }
+        CloseRequestResponse::HideWindow
+    });
+
+    ui.show()?;
+    // winit only creates the native window once the event loop runs, so the monitors are
+    // checked from the first timer callback rather than here.
+    if let Some(saved) = saved_position {
+        let ui_copy = ui_handle.clone();
+        Timer::single_shot(Duration::ZERO, move || {
+            if let Some(ui) = ui_copy.upgrade() { restore_window_position(&ui, saved); }
+        });
+    }
+    let result = slint::run_event_loop_until_quit();
+    let _ = ui.hide();
+    // Silence a looping alarm before the output stream is dropped with the state.
+    stop_alarm(&state.borrow().alarm);
+    state.borrow_mut().sync_dnd(false);
+    state.borrow().save_session(ui.get_is_running());
+    let summary = state.borrow_mut().day_summary_due(true);
+    if let Some(body) = summary { notify(&state.borrow().notification_title(), &body); }
+    state.borrow_mut().status_server = None;
+    state.borrow_mut().command_socket = None;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+    use Mode::{LongBreak as L, ShortBreak as S, Work as W};
+
+    /// Counts heap allocations per thread, so tests running alongside don't interfere.
+    struct CountingAlloc;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAlloc {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAlloc = CountingAlloc;
+
+    fn allocations(f: impl FnOnce()) -> usize {
+        let before = ALLOCATIONS.get();
+        f();
+        ALLOCATIONS.get() - before
+    }
+
+    /// A fresh state with no audio device. Stats and the session log go to a throwaway
+    /// data dir, set before anything resolves `data_dir`.
+    fn test_state(config: AppConfig) -> AppState {
+        static DIR: OnceLock<PathBuf> = OnceLock::new();
+        DIR.get_or_init(|| {
+            let dir = std::env::temp_dir().join(format!("pomodoro_timer_test_{}", std::process::id()));
+            std::env::set_var("POMODORO_DATA_DIR", &dir);
+            dir
+        });
+        AppState::with_output(ConfigFile::single(config), Stats::default(), None)
+    }
+
+    /// The modes a fresh state passes through over `completions` phases that run out.
+    fn phases(sessions_per_long_break: i32, completions: usize) -> Vec<Mode> {
+        let mut s = test_state(AppConfig { sessions_per_long_break, ..AppConfig::default() });
+        let mut seen = vec![s.mode];
+        for _ in 0..completions {
+            s.seconds_left = 0;
+            s.advance(false);
+            seen.push(s.mode);
+        }
+        seen
+    }
+
+    #[test]
+    fn long_break_after_every_fourth_session() {
+        assert_eq!(phases(4, 8), [W, S, W, S, W, S, W, L, W]);
+    }
+
+    #[test]
+    fn long_break_after_every_second_session() {
+        assert_eq!(phases(2, 6), [W, S, W, L, W, S, W]);
+    }
+
+    #[test]
+    fn interval_of_one_always_takes_the_long_break() {
+        assert_eq!(phases(1, 4), [W, L, W, L, W]);
+    }
+
+    #[test]
+    fn invalid_interval_falls_back_to_four() {
+        for interval in [0, -3] {
+            assert_eq!(phases(interval, 8), phases(4, 8));
+        }
+    }
+
+    #[test]
+    fn other_mode_length_change_leaves_paused_phase_alone() {
+        let mut s = test_state(AppConfig::default());
+        s.mode = S;
+        s.seconds_left = 100;
+        let phase_before = s.phase_seconds(s.mode);
+        let elapsed = s.phase_total() - s.seconds_left;
+        s.config.long_m = 30;
+        assert!(!s.refit_paused_phase(phase_before, elapsed));
+        assert_eq!(s.seconds_left, 100);
+    }
+
+    /// Skips one focus phase of a fresh state and reports (sessions_completed,
+    /// total_sessions, today_sessions), the next mode, focus credited and the notice.
+    fn skip_focus(count_skipped_work: bool) -> ((i32, i32, i32), Mode, i32, Option<String>) {
+        let mut s = test_state(AppConfig { count_skipped_work, sessions_per_long_break: 1, daily_goal: 1, ..AppConfig::default() });
+        s.seconds_left = 60;
+        let transition = s.advance(true);
+        let counters = (s.sessions_completed, s.stats.total_sessions, s.stats.today_sessions);
+        (counters, s.mode, s.stats.today_focus_seconds, transition.notice)
+    }
+
+    #[test]
+    fn skipped_focus_is_not_counted_by_default() {
+        let (counters, mode, focus, notice) = skip_focus(false);
+        assert_eq!(counters, (0, 0, 0));
+        assert_eq!(mode, S);
+        assert_eq!(focus, 0);
+        assert_eq!(notice, None);
+    }
+
+    #[test]
+    fn skipped_focus_counts_when_configured() {
+        let (counters, mode, focus, notice) = skip_focus(true);
+        assert_eq!(counters, (1, 1, 1));
+        // Counted, it completes the cycle of one and meets the goal of one.
+        assert_eq!(mode, L);
+        assert_eq!(focus, 0);
+        assert_eq!(notice.as_deref(), Some(tr("en", "notice.goal").replace("{n}", "1").as_str()));
+    }
+
+    #[test]
+    fn finished_focus_counts_under_both_policies() {
+        for count_skipped_work in [false, true] {
+            let mut s = test_state(AppConfig { count_skipped_work, ..AppConfig::default() });
+            s.seconds_left = 0;
+            s.advance(false);
+            assert_eq!((s.sessions_completed, s.stats.total_sessions, s.stats.today_sessions), (1, 1, 1));
+        }
+    }
+
+    /// One `settings-changed` round for a new focus length, as each keystroke makes it.
+    fn edit_work_length(s: &mut AppState, minutes: i32) {
+        let phase_before = s.phase_seconds(s.mode);
+        let elapsed = s.phase_elapsed();
+        s.config.work_m = minutes;
+        s.refit_paused_phase(phase_before, elapsed);
+    }
+
+    #[test]
+    fn paused_length_edit_keeps_elapsed_across_keystrokes() {
+        let mut s = test_state(AppConfig::default());
+        // Paused 10:00 into a 25-minute focus, then "30" typed one digit at a time.
+        s.seconds_left = 15 * 60;
+        edit_work_length(&mut s, 3);
+        assert_eq!(s.seconds_left, 1);
+        edit_work_length(&mut s, 30);
+        assert_eq!(s.seconds_left, 20 * 60);
+        // Back to the original length gives back the original countdown.
+        edit_work_length(&mut s, 25);
+        assert_eq!(s.seconds_left, 15 * 60);
+    }
+
+    #[test]
+    fn minutes_accept_surrounding_whitespace_only() {
+        assert!(matches!(Minutes::parse("  30  "), Minutes::Valid(30)));
+        assert!(matches!(Minutes::parse("30\n"), Minutes::Valid(30)));
+        assert!(matches!(Minutes::parse("30min"), Minutes::Invalid));
+        assert!(matches!(Minutes::parse(""), Minutes::Invalid));
+    }
+
+    #[test]
+    fn number_settings_report_what_does_not_parse() {
+        let mut errors = Vec::new();
+        assert_eq!(parse_setting("  30  ", "Goal", 8, &mut errors), 30);
+        assert_eq!(parse_setting("30\n", "Goal", 8, &mut errors), 30);
+        assert!(errors.is_empty());
+        assert_eq!(parse_setting("30min", "Goal", 8, &mut errors), 8);
+        assert_eq!(parse_setting("", "Goal", 8, &mut errors), 8);
+        assert_eq!(errors, ["Goal must be a whole number", "Goal must be a whole number"]);
+    }
+
+    #[test]
+    fn display_name_survives_paths_without_a_file_name() {
+        assert_eq!(display_name(Path::new("/music/bell.ogg")), "bell.ogg");
+        assert_eq!(display_name(Path::new("/")), "/");
+        assert_eq!(display_name(Path::new("/music/..")), "/music/..");
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            let path = Path::new(std::ffi::OsStr::from_bytes(b"/music/caf\xe9.ogg"));
+            assert_eq!(display_name(path), "caf\u{fffd}.ogg");
+        }
+    }
+
+    fn with_study_profile() -> AppState {
+        let mut s = test_state(AppConfig::default());
+        s.profiles.push(Profile { name: "Study".to_string(), config: AppConfig { work_m: 50, ..AppConfig::default() } });
+        s
+    }
+
+    #[test]
+    fn session_round_trips_the_active_profile() {
+        let mut s = with_study_profile();
+        assert!(s.switch_profile("Study", false));
+        // Something to resume, or the save clears the file instead.
+        s.sessions_completed = 1;
+        s.save_session(false);
+        let saved = load_session().expect("session was saved");
+        assert_eq!(saved.profile.as_deref(), Some("Study"));
+
+        let mut restored = with_study_profile();
+        restored.restore_profile(saved.profile.as_deref().unwrap());
+        assert_eq!(restored.active_profile, "Study");
+        assert_eq!(restored.config.work_m, 50);
+    }
+
+    #[test]
+    fn deleted_session_profile_falls_back_to_the_default() {
+        let mut s = with_study_profile();
+        assert!(s.switch_profile("Study", false));
+        s.restore_profile("Deleted");
+        assert_eq!(s.active_profile, DEFAULT_PROFILE);
+    }
+
+    /// 10k formats each way, counted and timed (`cargo test clock_text -- --nocapture`).
+    #[test]
+    fn clock_text_reuses_texts_across_phases() {
+        const N: i64 = 10_000;
+        let mut first = Vec::with_capacity(N as usize);
+        let mut again = Vec::with_capacity(N as usize);
+        let mut baseline = Vec::with_capacity(N as usize);
+        let started = Instant::now();
+        let first_allocations = allocations(|| (0..N).for_each(|s| first.push(clock_text(s))));
+        let first_time = started.elapsed();
+        let started = Instant::now();
+        let again_allocations = allocations(|| (0..N).for_each(|s| again.push(clock_text(s))));
+        let again_time = started.elapsed();
+        let started = Instant::now();
+        let baseline_allocations = allocations(|| (0..N).for_each(|s| baseline.push(SharedString::from(format!("{:02}:{:02}", s / 60, s % 60)))));
+        let baseline_time = started.elapsed();
+        eprintln!("10k clock texts: first run {first_allocations} allocations in {first_time:?}, \
+            later runs {again_allocations} in {again_time:?}; format! + into {baseline_allocations} in {baseline_time:?}");
+        // The other counts depend on std and slint internals; they're printed, not pinned.
+        assert_eq!(again_allocations, 0);
+        assert_eq!(first, baseline);
+        assert_eq!(again, baseline);
+        assert_eq!(clock_text(9_999), "166:39");
+        assert_eq!(clock_text(CLOCK_CACHE_LIMIT + 1), "180:01");
+    }
+
+    #[test]
+    fn short_phases_log_their_exact_length() {
+        assert_eq!(log_minutes(5), "0.0833");
+        assert_eq!(log_minutes(90), "1.5");
+        assert_eq!(log_minutes(1500), "25");
+        for seconds in [1, 5, 59, 90, 1499, 1500] {
+            assert_eq!(parse_log_seconds(&log_minutes(seconds)), Some(seconds));
+        }
+        // Rows written before the change hold whole minutes.
+        assert_eq!(parse_log_seconds("25"), Some(1500));
+        let today = NaiveDate::from_ymd_opt(2026, 10, 14).unwrap();
+        let stats = period_stats("7 days", &[(today, 5), (today, 5)], today, 7);
+        assert_eq!((stats.sessions, stats.focus.as_str()), (2, "0m"));
+    }
+}